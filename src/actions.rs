@@ -0,0 +1,53 @@
+use std::fmt;
+use std::fmt::Display;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Direction {
+    pub fn opposite(self) -> Self {
+        match self {
+            Direction::Up => Direction::Down,
+            Direction::Down => Direction::Up,
+            Direction::Left => Direction::Right,
+            Direction::Right => Direction::Left,
+        }
+    }
+}
+
+impl Display for Direction {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.write_str(match self {
+            Direction::Up => "up",
+            Direction::Down => "down",
+            Direction::Left => "left",
+            Direction::Right => "right",
+        })
+    }
+}
+
+/// What the player wants to do, independent of the key that was pressed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    Move(Direction),
+    Undo,
+    Redo,
+    Restart,
+    NextLevel,
+    Hint,
+    ToggleDeadSquares,
+    PanCamera(Direction),
+    ZoomIn,
+    ZoomOut,
+    ToggleCameraFollow,
+    FitCamera,
+    NextTheme,
+    ToggleReplayPause,
+    StepReplay,
+    ToggleReplayFastForward,
+}
@@ -1,3 +1,4 @@
+use crate::resources::SoundQueue;
 use ggez::audio::SoundSource;
 use ggez::{audio, Context};
 use specs::{World, WorldExt};
@@ -9,18 +10,17 @@ pub struct AudioStore {
 }
 
 impl AudioStore {
+    /// Does nothing for sounds that were not loaded, e.g. without an audio device
     pub fn play(&mut self, sound: &str) {
-        let _ = self
-            .sounds
-            .get_mut(sound)
-            .expect("Sound error")
-            .play_detached();
+        if let Some(source) = self.sounds.get_mut(sound) {
+            let _ = source.play_detached();
+        }
     }
 }
 
 pub fn initialize_sounds(world: &mut World, context: &mut Context) {
-    let mut audio_store = world.write_resource::<AudioStore>();
-    let sounds = ["correct", "incorrect", "wall"];
+    let mut audio_store = AudioStore::default();
+    let sounds = ["correct", "incorrect", "wall", "deadlock"];
 
     for sound in sounds.iter() {
         let name = sound.to_string();
@@ -29,4 +29,14 @@ pub fn initialize_sounds(world: &mut World, context: &mut Context) {
 
         audio_store.sounds.insert(name, source);
     }
+
+    world.insert(audio_store);
+}
+
+/// Plays the sounds queued since the last call, initialize_sounds has to come first
+pub fn play_sounds(world: &World) {
+    let mut audio_store = world.write_resource::<AudioStore>();
+    for sound in world.write_resource::<SoundQueue>().sounds.drain(..) {
+        audio_store.play(sound);
+    }
 }
@@ -0,0 +1,101 @@
+use crate::actions::Direction;
+use crate::components::{Box, BoxColor, BoxSpot, Player, Position, Wall};
+use crate::resources::MapSize;
+use specs::{Join, ReadStorage, World, WorldExt};
+use std::collections::{HashMap, HashSet};
+
+/// (x, y) of a tile
+pub type Cell = (u8, u8);
+
+pub const DIRECTIONS: [Direction; 4] = [
+    Direction::Up,
+    Direction::Down,
+    Direction::Left,
+    Direction::Right,
+];
+
+/// A plain snapshot of the level, for analysis that does not need the ECS
+#[derive(Debug, Clone)]
+pub struct Board {
+    pub width: u8,
+    pub height: u8,
+    pub walls: HashSet<Cell>,
+    pub spots: HashMap<Cell, BoxColor>,
+    pub boxes: HashMap<Cell, BoxColor>,
+    pub player: Cell,
+}
+
+impl Board {
+    /// Reads the current positions, None when there is no level loaded
+    pub fn from_world(world: &World) -> Option<Self> {
+        Self::from_storages(
+            &world.read_resource::<MapSize>(),
+            &world.read_storage::<Position>(),
+            &world.read_storage::<Player>(),
+            &world.read_storage::<Wall>(),
+            &world.read_storage::<BoxSpot>(),
+            &world.read_storage::<Box>(),
+        )
+    }
+
+    /// Same as from_world, for systems that already hold the storages
+    pub fn from_storages(
+        map_size: &MapSize,
+        positions: &ReadStorage<Position>,
+        players: &ReadStorage<Player>,
+        walls: &ReadStorage<Wall>,
+        box_spots: &ReadStorage<BoxSpot>,
+        boxes: &ReadStorage<Box>,
+    ) -> Option<Self> {
+        let player = (positions, players)
+            .join()
+            .map(|(position, _player)| (position.x, position.y))
+            .next()?;
+        let walls = (positions, walls)
+            .join()
+            .map(|(position, _wall)| (position.x, position.y))
+            .collect();
+        let spots = (positions, box_spots)
+            .join()
+            .map(|(position, box_spot)| ((position.x, position.y), box_spot.color))
+            .collect();
+        let boxes = (positions, boxes)
+            .join()
+            .map(|(position, the_box)| ((position.x, position.y), the_box.color))
+            .collect();
+
+        Some(Self {
+            width: map_size.width,
+            height: map_size.height,
+            walls,
+            spots,
+            boxes,
+            player,
+        })
+    }
+
+    /// The next cell in a direction, None past the edge of the map
+    pub fn neighbor(&self, cell: Cell, direction: Direction) -> Option<Cell> {
+        let (x, y) = cell;
+        let next = match direction {
+            Direction::Up => (x, y.checked_sub(1)?),
+            Direction::Down => (x, y.checked_add(1)?),
+            Direction::Left => (x.checked_sub(1)?, y),
+            Direction::Right => (x.checked_add(1)?, y),
+        };
+
+        if next.0 < self.width && next.1 < self.height {
+            Some(next)
+        } else {
+            None
+        }
+    }
+
+    /// Walls and the edge of the map both stop boxes and the player
+    pub fn is_blocked(&self, cell: Option<Cell>) -> bool {
+        match cell {
+            Some(cell) => self.walls.contains(&cell),
+            None => true,
+        }
+    }
+}
@@ -1,6 +1,9 @@
+use crate::actions::Direction;
 use specs::{Component, NullStorage, VecStorage, World, WorldExt};
+use std::collections::HashMap;
 use std::fmt;
 use std::fmt::Display;
+use std::time::Duration;
 
 #[derive(Debug, Component, Clone, Copy)]
 #[storage(VecStorage)]
@@ -10,37 +13,149 @@ pub struct Position {
     pub z: u8,
 }
 
+/// Slides the sprite from the tile it left to its Position, only used for drawing
+#[derive(Debug, Component, Clone, Copy)]
+#[storage(VecStorage)]
+pub struct Tween {
+    pub from: (u8, u8),
+    pub started_at: Duration,
+    pub duration: Duration,
+}
+
+impl Tween {
+    /// 0.0 when the move starts, 1.0 once it reached the new tile
+    pub fn progress(&self, now: Duration) -> f32 {
+        if self.duration == Duration::default() {
+            return 1.0;
+        }
+
+        let elapsed = now.checked_sub(self.started_at).unwrap_or_default();
+        f32::min(1.0, elapsed.as_secs_f32() / self.duration.as_secs_f32())
+    }
+
+    pub fn is_finished(&self, now: Duration) -> bool {
+        self.progress(now) >= 1.0
+    }
+
+    /// Where the sprite is on its way to `to`, in tiles
+    pub fn position(&self, to: &Position, now: Duration) -> (f32, f32) {
+        let progress = self.progress(now);
+        let from_x = self.from.0 as f32;
+        let from_y = self.from.1 as f32;
+        (
+            from_x + (to.x as f32 - from_x) * progress,
+            from_y + (to.y as f32 - from_y) * progress,
+        )
+    }
+}
+
+pub const DEFAULT_STATE: &str = "default";
+
+/// Frames for every named animation state, only the current state is drawn
 #[derive(Debug, Component)]
 #[storage(VecStorage)]
 pub struct Renderable {
-    paths: Vec<String>,
+    states: HashMap<String, Vec<String>>,
+    state: String,
 }
 
 impl Renderable {
     pub fn new_static(path: String) -> Self {
-        Self { paths: vec![path] }
+        Self::new_animated(vec![path])
     }
 
     pub fn new_animated(paths: Vec<String>) -> Self {
-        Self { paths }
+        Self::new_states(DEFAULT_STATE, vec![(DEFAULT_STATE.to_string(), paths)])
+    }
+
+    /// Starts in `state`, which has to be one of the given states
+    pub fn new_states(state: &str, states: Vec<(String, Vec<String>)>) -> Self {
+        let states: HashMap<_, _> = states.into_iter().collect();
+        if !states.contains_key(state) {
+            panic!("Missing animation state {}", state);
+        }
+
+        Self {
+            states,
+            state: state.to_string(),
+        }
     }
 
-    pub fn kind(&self) -> RenderableKind {
-        match self.paths.len() {
-            0 => panic!("Missing path"),
-            1 => RenderableKind::Static,
-            _ => RenderableKind::Animated,
+    /// Unknown states are ignored so entities without them keep their sprite.
+    /// Returns whether the state changed.
+    pub fn set_state(&mut self, state: &str) -> bool {
+        if self.state == state || !self.states.contains_key(state) {
+            return false;
         }
+
+        self.state = state.to_string();
+        true
+    }
+
+    pub fn state(&self) -> &str {
+        &self.state
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.paths().len()
     }
 
     pub fn path(&self, path_index: usize) -> String {
-        self.paths[path_index % self.paths.len()].clone()
+        let paths = self.paths();
+        paths[path_index % paths.len()].clone()
+    }
+
+    fn paths(&self) -> &[String] {
+        &self.states[&self.state]
     }
 }
 
-pub enum RenderableKind {
-    Static,
-    Animated,
+#[derive(Debug, Clone, PartialEq)]
+pub enum AnimationMode {
+    Loop,
+    // stops on the last frame, or carries on looping the `then` state
+    Once { then: Option<String> },
+}
+
+/// The frame of its Renderable state an entity shows, advanced by the AnimationSystem
+#[derive(Debug, Component)]
+#[storage(VecStorage)]
+pub struct Animation {
+    pub frame_duration: Duration,
+    // moves a loop along so entities don't all change frames together
+    pub offset: Duration,
+    pub started_at: Duration,
+    pub mode: AnimationMode,
+    pub frame: usize,
+}
+
+impl Animation {
+    pub fn looping(frame_duration: Duration, offset: Duration) -> Self {
+        Self {
+            frame_duration,
+            offset,
+            started_at: Duration::default(),
+            mode: AnimationMode::Loop,
+            frame: 0,
+        }
+    }
+
+    /// Plays from the first frame, a start in the future holds the first frame until then
+    pub fn restart(&mut self, started_at: Duration, mode: AnimationMode) {
+        self.started_at = started_at;
+        self.mode = mode;
+        self.frame = 0;
+    }
+
+    /// Frames played since the start, not wrapped around
+    pub fn elapsed_frames(&self, now: Duration) -> usize {
+        let now = match self.mode {
+            AnimationMode::Loop => now + self.offset,
+            AnimationMode::Once { .. } => now,
+        };
+        let elapsed = now.checked_sub(self.started_at).unwrap_or_default();
+        (elapsed.as_millis() / self.frame_duration.as_millis().max(1)) as usize
+    }
 }
 
 #[derive(Component)]
@@ -49,10 +164,19 @@ pub struct Wall {}
 
 #[derive(Component)]
 #[storage(VecStorage)]
-pub struct Player {}
+pub struct Floor {}
+
+#[derive(Component)]
+#[storage(VecStorage)]
+pub struct Player {
+    // the direction of the last move, also when it was blocked
+    pub facing: Direction,
+}
 
-#[derive(PartialEq)]
+// XSB levels have a single colour, their boxes and spots use the default one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
 pub enum BoxColor {
+    #[default]
     Red,
     Blue,
 }
@@ -89,8 +213,11 @@ pub struct Immovable;
 pub fn register_components(world: &mut World) {
     world.register::<Position>();
     world.register::<Renderable>();
+    world.register::<Tween>();
+    world.register::<Animation>();
     world.register::<Player>();
     world.register::<Wall>();
+    world.register::<Floor>();
     world.register::<Box>();
     world.register::<BoxSpot>();
     world.register::<Movable>();
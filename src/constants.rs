@@ -1,3 +1,30 @@
-pub const MAP_WIDTH: u8 = 8;
-pub const MAP_HEIGHT: u8 = 9;
+use std::time::Duration;
+
 pub const TILE_WIDTH: f32 = 32.0;
+pub const WINDOW_WIDTH: f32 = 800.0;
+pub const WINDOW_HEIGHT: f32 = 600.0;
+// The HUD column on the right of the window, levels are fitted next to it
+pub const HUD_WIDTH: f32 = 275.0;
+pub const MIN_ZOOM: f32 = 0.25;
+pub const MAX_ZOOM: f32 = 4.0;
+pub const ZOOM_STEP: f32 = 1.25;
+pub const RESOURCES_PATH: &str = "./resources";
+pub const KEY_BINDINGS_FILE: &str = "keybindings.toml";
+pub const DEFAULT_LEVEL_PACK_PATH: &str = "./resources/levels";
+pub const SETTINGS_PATH: &str = "./resources/settings.toml";
+pub const SOLUTIONS_PATH: &str = "./solutions";
+pub const THEMES_PATH: &str = "./resources/themes";
+pub const DEFAULT_THEME: &str = "default";
+// A level pack directory with this file uses it as its theme
+pub const THEME_FILE: &str = "theme.toml";
+// Actions given while a move is animating, more are dropped
+pub const MAX_BUFFERED_ACTIONS: usize = 2;
+pub const FRAME_DURATION: Duration = Duration::from_millis(250);
+// Replay steps per second, fast-forward multiplies it
+pub const REPLAY_SPEED: f32 = 4.0;
+pub const REPLAY_FAST_FORWARD: f32 = 8.0;
+pub const SOLVER_MAX_NODES: usize = 1_000_000;
+pub const SOLVER_MAX_TIME: Duration = Duration::from_secs(30);
+// Hints are searched while the game goes on, they give up sooner to arrive in time
+pub const HINT_MAX_NODES: usize = 200_000;
+pub const HINT_MAX_TIME: Duration = Duration::from_secs(2);
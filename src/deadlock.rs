@@ -0,0 +1,193 @@
+use crate::actions::Direction;
+use crate::board::{Board, Cell, DIRECTIONS};
+use crate::components::BoxColor;
+use crate::solver::reachable_cells;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// Floor cells a box of a colour can never be pushed out of onto one of
+/// its spots. Only depends on the walls and spots, so it is computed once
+/// per level.
+#[derive(Debug, Default)]
+pub struct DeadSquares {
+    pub cells: HashMap<BoxColor, HashSet<Cell>>,
+}
+
+impl DeadSquares {
+    pub fn new(board: &Board) -> Self {
+        // Everything the player can walk to, ignoring the boxes
+        let floor = reachable_cells(board, &HashSet::new(), board.player);
+        let colors: HashSet<BoxColor> = board.boxes.values().copied().collect();
+
+        let cells = colors
+            .into_iter()
+            .map(|color| {
+                let live = pull_from_spots(board, color);
+                let dead = floor.difference(&live).copied().collect();
+                (color, dead)
+            })
+            .collect();
+
+        Self { cells }
+    }
+
+    pub fn is_dead(&self, cell: Cell, color: BoxColor) -> bool {
+        matches!(self.cells.get(&color), Some(cells) if cells.contains(&cell))
+    }
+}
+
+// Pulls boxes backwards from every spot of the colour, a box can reach a
+// spot from exactly the cells it can be pulled to
+fn pull_from_spots(board: &Board, color: BoxColor) -> HashSet<Cell> {
+    let mut live = HashSet::new();
+    let mut queue: VecDeque<Cell> = board
+        .spots
+        .iter()
+        .filter(|(_cell, spot_color)| **spot_color == color)
+        .map(|(cell, _color)| *cell)
+        .collect();
+
+    while let Some(cell) = queue.pop_front() {
+        if !live.insert(cell) {
+            continue;
+        }
+
+        for direction in DIRECTIONS.iter() {
+            // The player walks backwards and drags the box along
+            let target = board.neighbor(cell, *direction);
+            let player = target.and_then(|target| board.neighbor(target, *direction));
+            if let (Some(target), false) = (target, board.is_blocked(player)) {
+                if !board.walls.contains(&target) {
+                    queue.push_back(target);
+                }
+            }
+        }
+    }
+
+    live
+}
+
+/// Boxes that can never be pushed onto a spot of their colour again.
+/// A box is stuck on a dead square, or when it can move along neither axis
+/// and is not on a matching spot, which covers boxes lined up against a wall
+/// and 2x2 blocks of boxes.
+pub fn find_deadlocks(board: &Board, dead_squares: &DeadSquares) -> HashSet<Cell> {
+    let is_frozen_along =
+        |cell, direction| is_frozen(board, dead_squares, cell, direction, &mut HashSet::new());
+
+    board
+        .boxes
+        .iter()
+        .filter(|(cell, color)| board.spots.get(cell) != Some(color))
+        .filter(|(cell, color)| {
+            dead_squares.is_dead(**cell, **color)
+                || (is_frozen_along(**cell, Direction::Left)
+                    && is_frozen_along(**cell, Direction::Up))
+        })
+        .map(|(cell, _color)| *cell)
+        .collect()
+}
+
+// Whether the box can never move along the axis of the direction.
+// Boxes already being checked count as walls, otherwise two boxes side by
+// side would each wait on the other.
+fn is_frozen(
+    board: &Board,
+    dead_squares: &DeadSquares,
+    cell: Cell,
+    direction: Direction,
+    checking: &mut HashSet<Cell>,
+) -> bool {
+    let sides = [
+        board.neighbor(cell, direction),
+        board.neighbor(cell, direction.opposite()),
+    ];
+    if sides.iter().any(|side| board.is_blocked(*side)) {
+        return true;
+    }
+
+    // Moving either way would only push it onto a dead square
+    let color = board.boxes[&cell];
+    if sides
+        .iter()
+        .flatten()
+        .all(|side| dead_squares.is_dead(*side, color))
+    {
+        return true;
+    }
+
+    checking.insert(cell);
+    let cross_direction = match direction {
+        Direction::Up | Direction::Down => Direction::Left,
+        Direction::Left | Direction::Right => Direction::Up,
+    };
+    let is_frozen = sides.iter().flatten().any(|side| {
+        checking.contains(side)
+            || (board.boxes.contains_key(side)
+                && is_frozen(board, dead_squares, *side, cross_direction, checking))
+    });
+    checking.remove(&cell);
+
+    is_frozen
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::load;
+
+    fn deadlocks(level: &str) -> HashSet<Cell> {
+        let board = Board::from_world(&load(level)).expect("No level loaded");
+        find_deadlocks(&board, &DeadSquares::new(&board))
+    }
+
+    fn cells(cells: &[Cell]) -> HashSet<Cell> {
+        cells.iter().copied().collect()
+    }
+
+    #[test]
+    fn dead_squares_are_the_cells_no_spot_can_be_reached_from() {
+        let board = Board::from_world(&load("######\n#  $.#\n# @  #\n######")).unwrap();
+        let dead_squares = DeadSquares::new(&board);
+
+        assert!(dead_squares.is_dead((1, 1), BoxColor::Red));
+        assert!(dead_squares.is_dead((2, 2), BoxColor::Red));
+        assert!(!dead_squares.is_dead((2, 1), BoxColor::Red));
+        assert!(!dead_squares.is_dead((4, 1), BoxColor::Red));
+    }
+
+    #[test]
+    fn box_in_a_corner() {
+        let level = "#####\n#$  #\n# @.#\n#####";
+
+        assert_eq!(deadlocks(level), cells(&[(1, 1)]));
+    }
+
+    #[test]
+    fn boxes_side_by_side_on_a_wall() {
+        // Both cells can still reach a spot along the wall, only the pair is stuck
+        let level = "#######\n#.$$. #\n#  @  #\n#######";
+
+        assert_eq!(deadlocks(level), cells(&[(2, 1), (3, 1)]));
+    }
+
+    #[test]
+    fn block_of_four_boxes() {
+        let level = "########\n#      #\n# $$   #\n# $$ @ #\n#  ....#\n#      #\n########";
+
+        assert_eq!(deadlocks(level), cells(&[(2, 2), (3, 2), (2, 3), (3, 3)]));
+    }
+
+    #[test]
+    fn frozen_box_on_its_spot_is_fine() {
+        let level = "########\n#      #\n# *$   #\n# $$ @ #\n#  ... #\n#      #\n########";
+
+        assert_eq!(deadlocks(level), cells(&[(3, 2), (2, 3), (3, 3)]));
+    }
+
+    #[test]
+    fn box_that_can_move() {
+        let level = "######\n#    #\n# $@ #\n#  . #\n######";
+
+        assert!(deadlocks(level).is_empty());
+    }
+}
@@ -1,59 +1,75 @@
+use crate::actions::Direction;
 use crate::components::*;
+use crate::constants::FRAME_DURATION;
+use crate::theme::{Theme, ThemeItem};
 use specs::{Builder, Entity, World, WorldExt};
+use std::time::Duration;
 
 pub fn create_wall(world: &mut World, position: Position) -> Entity {
+    let renderable = themed(world, ThemeItem::Wall, DEFAULT_STATE);
     world
         .create_entity()
         .with(Position { z: 10, ..position })
-        .with(Renderable::new_static(String::from("/images/wall.png")))
+        .with(renderable)
         .with(Wall {})
         .with(Immovable {})
         .build()
 }
 
 pub fn create_floor(world: &mut World, position: Position) -> Entity {
+    let renderable = themed(world, ThemeItem::Floor, DEFAULT_STATE);
     world
         .create_entity()
         .with(Position { z: 5, ..position })
-        .with(Renderable::new_static(String::from("/images/floor.png")))
+        .with(renderable)
+        .with(Floor {})
         .build()
 }
 
 pub fn create_box(world: &mut World, position: Position, color: BoxColor) -> Entity {
+    // "settle" is played once when the box lands on its spot
+    let renderable = themed(world, ThemeItem::Box(color), DEFAULT_STATE);
     world
         .create_entity()
         .with(Position { z: 10, ..position })
-        .with(Renderable::new_animated(vec![
-            format!("/images/box_{}_1.png", color),
-            format!("/images/box_{}_2.png", color),
-        ]))
+        .with(renderable)
+        .with(Animation::looping(FRAME_DURATION, box_offset(position)))
         .with(Box { color })
         .with(Movable {})
         .build()
 }
 
 pub fn create_box_spot(world: &mut World, position: Position, color: BoxColor) -> Entity {
+    let renderable = themed(world, ThemeItem::BoxSpot(color), DEFAULT_STATE);
     world
         .create_entity()
         .with(Position { z: 10, ..position })
-        .with(Renderable::new_static(format!(
-            "/images/box_spot_{}.png",
-            color
-        )))
+        .with(renderable)
         .with(BoxSpot { color })
         .build()
 }
 
 pub fn create_player(world: &mut World, position: Position) -> Entity {
+    // idle_up, walk_left, ... see PlayerSpriteSystem
+    let renderable = themed(world, ThemeItem::Player, "idle_down");
     world
         .create_entity()
         .with(Position { z: 10, ..position })
-        .with(Renderable::new_animated(vec![
-            String::from("/images/player_1.png"),
-            String::from("/images/player_2.png"),
-            String::from("/images/player_3.png"),
-        ]))
-        .with(Player {})
+        .with(renderable)
+        .with(Animation::looping(FRAME_DURATION, Duration::default()))
+        .with(Player {
+            facing: Direction::Down,
+        })
         .with(Movable {})
         .build()
 }
+
+fn themed(world: &World, item: ThemeItem, state: &str) -> Renderable {
+    world.read_resource::<Theme>().renderable(item, state)
+}
+
+// Neighbouring boxes are out of step with each other
+fn box_offset(position: Position) -> Duration {
+    let step = (position.x as u32 + position.y as u32) % 2;
+    FRAME_DURATION * step
+}
@@ -3,16 +3,26 @@ pub type EntityId = u32;
 #[derive(Debug)]
 pub struct EntityMoved {
     pub id: EntityId,
+    // the tile it moved from
+    pub from: (u8, u8),
 }
 
 #[derive(Debug)]
 pub struct BoxPlacedOnSpot {
+    pub id: EntityId,
     pub is_correct_spot: bool,
 }
 
+/// Boxes that got stuck off their spots with the last push
+#[derive(Debug)]
+pub struct Deadlock {
+    pub boxes: Vec<EntityId>,
+}
+
 #[derive(Debug)]
 pub enum Event {
     PlayerHitObstacle,
     EntityMoved(EntityMoved),
     BoxPlacedOnSpot(BoxPlacedOnSpot),
+    Deadlock(Deadlock),
 }
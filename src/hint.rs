@@ -0,0 +1,155 @@
+use crate::board::Board;
+use crate::constants::{HINT_MAX_NODES, HINT_MAX_TIME};
+use crate::resources::{Gameplay, Hint, HintPush};
+use crate::solution::parse_lurd;
+use crate::solver::{solve, SolverLimits};
+use specs::{World, WorldExt};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// The solver running on its own thread, so the window keeps drawing while it searches
+#[derive(Default)]
+pub struct HintSearch {
+    receiver: Option<Mutex<Receiver<Hint>>>,
+    // tells the solver to give up, its result would be for other positions
+    cancel: Arc<AtomicBool>,
+}
+
+impl HintSearch {
+    pub fn is_running(&self) -> bool {
+        self.receiver.is_some()
+    }
+
+    /// Stops the running search, when something moves or the level changes
+    pub fn cancel(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+        self.receiver = None;
+    }
+
+    /// The hint once the search is done, None while it is still running
+    pub fn poll(&mut self) -> Option<Hint> {
+        let receiver = self
+            .receiver
+            .as_mut()?
+            .get_mut()
+            .expect("Hint search error");
+        let hint = match receiver.try_recv() {
+            Ok(hint) => hint,
+            Err(TryRecvError::Empty) => return None,
+            Err(TryRecvError::Disconnected) => Hint {
+                push: None,
+                error: Some(String::from("The hint search failed")),
+            },
+        };
+
+        self.receiver = None;
+        Some(hint)
+    }
+}
+
+/// Starts solving the level from the current positions, the HintSystem
+/// highlights the first push of the solution once it is found
+pub fn show_hint(world: &mut World) {
+    // Asking again doesn't make the running search any faster
+    if world.read_resource::<HintSearch>().is_running() {
+        return;
+    }
+
+    let board = match Board::from_world(world) {
+        Some(board) => board,
+        None => return,
+    };
+
+    let (sender, receiver) = mpsc::channel();
+    let cancel = Arc::new(AtomicBool::new(false));
+    let search_cancel = Arc::clone(&cancel);
+    thread::spawn(move || {
+        // Fails when nobody waits for the hint anymore
+        let _ = sender.send(find_hint(&board, &search_cancel));
+    });
+
+    *world.write_resource::<HintSearch>() = HintSearch {
+        receiver: Some(Mutex::new(receiver)),
+        cancel,
+    };
+    *world.write_resource::<Hint>() = Hint::default();
+    world.write_resource::<Gameplay>().hints_count += 1;
+}
+
+fn find_hint(board: &Board, cancel: &AtomicBool) -> Hint {
+    let limits = SolverLimits {
+        max_nodes: HINT_MAX_NODES,
+        max_time: HINT_MAX_TIME,
+    };
+
+    match solve(board, &limits, cancel) {
+        Ok(lurd) => Hint {
+            push: first_push(board, &lurd),
+            error: None,
+        },
+        Err(error) => Hint {
+            push: None,
+            error: Some(error.to_string()),
+        },
+    }
+}
+
+// Walks the player along the solution until it pushes something
+fn first_push(board: &Board, lurd: &str) -> Option<HintPush> {
+    let mut player = board.player;
+
+    for (direction, is_push) in parse_lurd(lurd).ok()? {
+        let next = board.neighbor(player, direction)?;
+        if is_push {
+            return Some((next, direction));
+        }
+        player = next;
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actions::{Action, Direction};
+    use crate::testing::{load, play, RIGHT};
+    use crate::{perform_action, run_gameplay};
+    use std::time::Duration;
+
+    const LEVEL: &str = "#######\n#@ $ .#\n#######";
+
+    #[test]
+    fn the_hint_arrives_while_the_game_goes_on() {
+        let mut world = load(LEVEL);
+        perform_action(&mut world, Action::Hint);
+
+        for _ in 0..500 {
+            run_gameplay(&world);
+            if !world.read_resource::<HintSearch>().is_running() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        assert_eq!(
+            world.read_resource::<Hint>().push,
+            Some(((3, 1), Direction::Right))
+        );
+        assert_eq!(world.read_resource::<Gameplay>().hints_count, 1);
+    }
+
+    #[test]
+    fn a_move_cancels_the_search() {
+        let mut world = load(LEVEL);
+        perform_action(&mut world, Action::Hint);
+        let cancel = Arc::clone(&world.read_resource::<HintSearch>().cancel);
+
+        play(&mut world, &[RIGHT]);
+
+        assert!(cancel.load(Ordering::Relaxed));
+        assert!(!world.read_resource::<HintSearch>().is_running());
+    }
+}
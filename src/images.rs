@@ -0,0 +1,32 @@
+use crate::constants::TILE_WIDTH;
+use ggez::graphics::{Color, Image};
+use ggez::Context;
+use std::collections::HashMap;
+
+/// Images loaded on first use, keyed by path
+#[derive(Default)]
+pub struct ImageCache {
+    pub images: HashMap<String, Image>,
+}
+
+impl ImageCache {
+    /// A missing or broken image is drawn as a magenta tile instead, and only
+    /// reported the first time
+    pub fn get(&mut self, context: &mut Context, path: &str) -> Image {
+        if let Some(image) = self.images.get(path) {
+            return image.clone();
+        }
+
+        let image = Image::new(context, path).unwrap_or_else(|error| {
+            eprintln!("Could not load image {}: {}", path, error);
+            placeholder(context)
+        });
+        self.images.insert(path.to_string(), image.clone());
+        image
+    }
+}
+
+fn placeholder(context: &mut Context) -> Image {
+    let color = Color::new(1.0, 0.0, 1.0, 1.0);
+    Image::solid(context, TILE_WIDTH as u16, color).expect("Placeholder image error")
+}
@@ -0,0 +1,373 @@
+use crate::actions::{Action, Direction};
+use crate::constants::KEY_BINDINGS_FILE;
+use ggez::event::KeyCode;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::fmt::Display;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// Built into the game, so it still has bindings without the resources directory
+const DEFAULT_KEY_BINDINGS_TOML: &str = include_str!("../resources/keybindings.toml");
+
+const ACTION_NAMES: [(&str, Action); 22] = [
+    ("move_up", Action::Move(Direction::Up)),
+    ("move_down", Action::Move(Direction::Down)),
+    ("move_left", Action::Move(Direction::Left)),
+    ("move_right", Action::Move(Direction::Right)),
+    ("undo", Action::Undo),
+    ("redo", Action::Redo),
+    ("restart", Action::Restart),
+    ("next_level", Action::NextLevel),
+    ("hint", Action::Hint),
+    ("toggle_dead_squares", Action::ToggleDeadSquares),
+    ("pan_up", Action::PanCamera(Direction::Up)),
+    ("pan_down", Action::PanCamera(Direction::Down)),
+    ("pan_left", Action::PanCamera(Direction::Left)),
+    ("pan_right", Action::PanCamera(Direction::Right)),
+    ("zoom_in", Action::ZoomIn),
+    ("zoom_out", Action::ZoomOut),
+    ("toggle_camera_follow", Action::ToggleCameraFollow),
+    ("fit_camera", Action::FitCamera),
+    ("next_theme", Action::NextTheme),
+    ("toggle_replay_pause", Action::ToggleReplayPause),
+    ("step_replay", Action::StepReplay),
+    (
+        "toggle_replay_fast_forward",
+        Action::ToggleReplayFastForward,
+    ),
+];
+
+const KEY_NAMES: [(&str, KeyCode); 66] = [
+    ("A", KeyCode::A),
+    ("B", KeyCode::B),
+    ("C", KeyCode::C),
+    ("D", KeyCode::D),
+    ("E", KeyCode::E),
+    ("F", KeyCode::F),
+    ("G", KeyCode::G),
+    ("H", KeyCode::H),
+    ("I", KeyCode::I),
+    ("J", KeyCode::J),
+    ("K", KeyCode::K),
+    ("L", KeyCode::L),
+    ("M", KeyCode::M),
+    ("N", KeyCode::N),
+    ("O", KeyCode::O),
+    ("P", KeyCode::P),
+    ("Q", KeyCode::Q),
+    ("R", KeyCode::R),
+    ("S", KeyCode::S),
+    ("T", KeyCode::T),
+    ("U", KeyCode::U),
+    ("V", KeyCode::V),
+    ("W", KeyCode::W),
+    ("X", KeyCode::X),
+    ("Y", KeyCode::Y),
+    ("Z", KeyCode::Z),
+    ("0", KeyCode::Key0),
+    ("1", KeyCode::Key1),
+    ("2", KeyCode::Key2),
+    ("3", KeyCode::Key3),
+    ("4", KeyCode::Key4),
+    ("5", KeyCode::Key5),
+    ("6", KeyCode::Key6),
+    ("7", KeyCode::Key7),
+    ("8", KeyCode::Key8),
+    ("9", KeyCode::Key9),
+    ("F1", KeyCode::F1),
+    ("F2", KeyCode::F2),
+    ("F3", KeyCode::F3),
+    ("F4", KeyCode::F4),
+    ("F5", KeyCode::F5),
+    ("F6", KeyCode::F6),
+    ("F7", KeyCode::F7),
+    ("F8", KeyCode::F8),
+    ("F9", KeyCode::F9),
+    ("F10", KeyCode::F10),
+    ("F11", KeyCode::F11),
+    ("F12", KeyCode::F12),
+    ("Up", KeyCode::Up),
+    ("Down", KeyCode::Down),
+    ("Left", KeyCode::Left),
+    ("Right", KeyCode::Right),
+    ("Space", KeyCode::Space),
+    ("Back", KeyCode::Back),
+    ("Return", KeyCode::Return),
+    ("Escape", KeyCode::Escape),
+    ("Tab", KeyCode::Tab),
+    ("Period", KeyCode::Period),
+    ("Comma", KeyCode::Comma),
+    ("Minus", KeyCode::Minus),
+    ("Equals", KeyCode::Equals),
+    ("Home", KeyCode::Home),
+    ("End", KeyCode::End),
+    ("PageUp", KeyCode::PageUp),
+    ("PageDown", KeyCode::PageDown),
+    ("Delete", KeyCode::Delete),
+];
+
+pub struct KeyBindings {
+    actions: HashMap<KeyCode, Action>,
+    // in the order the bindings list them, for showing keys in the HUD
+    key_names: HashMap<Action, Vec<&'static str>>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        let (key_bindings, _conflicts) =
+            parse_bindings(Path::new(KEY_BINDINGS_FILE), &default_bindings())
+                .expect("Invalid default key bindings");
+        key_bindings
+    }
+}
+
+impl KeyBindings {
+    pub fn action(&self, key: KeyCode) -> Option<Action> {
+        self.actions.get(&key).cloned()
+    }
+
+    /// The first key bound to the action, as it is written in the bindings file
+    pub fn key_name(&self, action: Action) -> Option<&'static str> {
+        self.key_names
+            .get(&action)
+            .and_then(|names| names.first())
+            .copied()
+    }
+}
+
+/// A key listed under several actions, the first action keeps it
+#[derive(Debug)]
+pub struct KeyConflict {
+    pub key: String,
+    pub actions: Vec<String>,
+}
+
+impl Display for KeyConflict {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            fmt,
+            "Key {} is bound to {}, using {}",
+            self.key,
+            self.actions.join(", "),
+            self.actions[0]
+        )
+    }
+}
+
+#[derive(Debug)]
+pub enum KeyBindingsError {
+    Io {
+        path: PathBuf,
+        error: io::Error,
+    },
+    Parse {
+        path: PathBuf,
+        error: toml::de::Error,
+    },
+    UnknownAction {
+        path: PathBuf,
+        name: String,
+    },
+    UnknownKey {
+        path: PathBuf,
+        name: String,
+    },
+}
+
+impl Display for KeyBindingsError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            KeyBindingsError::Io { path, error } => {
+                write!(
+                    fmt,
+                    "Could not read key bindings {}: {}",
+                    path.display(),
+                    error
+                )
+            }
+            KeyBindingsError::Parse { path, error } => {
+                write!(fmt, "Invalid key bindings {}: {}", path.display(), error)
+            }
+            KeyBindingsError::UnknownAction { path, name } => {
+                write!(fmt, "Unknown action {:?} in {}", name, path.display())
+            }
+            KeyBindingsError::UnknownKey { path, name } => {
+                write!(fmt, "Unknown key {:?} in {}", name, path.display())
+            }
+        }
+    }
+}
+
+impl std::error::Error for KeyBindingsError {}
+
+/// Loads the first bindings file that exists, the defaults are used when none does.
+/// Actions the file leaves out keep their default keys, unless the file took them.
+/// Conflicting bindings don't stop the game, they are returned to be reported.
+pub fn load_key_bindings(
+    paths: &[PathBuf],
+) -> Result<(KeyBindings, Vec<KeyConflict>), KeyBindingsError> {
+    let path = match paths.iter().find(|path| path.is_file()) {
+        Some(path) => path,
+        None => return Ok((KeyBindings::default(), Vec::new())),
+    };
+
+    let content = fs::read_to_string(path).map_err(|error| KeyBindingsError::Io {
+        path: path.to_path_buf(),
+        error,
+    })?;
+
+    parse_user_bindings(path, &content)
+}
+
+// Adds the default keys to the bindings a user file lists
+fn parse_user_bindings(
+    path: &Path,
+    content: &str,
+) -> Result<(KeyBindings, Vec<KeyConflict>), KeyBindingsError> {
+    // Sorted so that conflicts are resolved the same way on every run
+    let mut bindings: BTreeMap<String, Vec<String>> =
+        toml::from_str(content).map_err(|error| KeyBindingsError::Parse {
+            path: path.to_path_buf(),
+            error,
+        })?;
+
+    let used_keys: HashSet<String> = bindings
+        .values()
+        .flatten()
+        .map(|key_name| key_name.to_lowercase())
+        .collect();
+    for (action_name, key_names) in default_bindings() {
+        bindings.entry(action_name).or_insert_with(|| {
+            key_names
+                .into_iter()
+                .filter(|key_name| !used_keys.contains(&key_name.to_lowercase()))
+                .collect()
+        });
+    }
+
+    parse_bindings(path, &bindings)
+}
+
+fn default_bindings() -> BTreeMap<String, Vec<String>> {
+    toml::from_str(DEFAULT_KEY_BINDINGS_TOML).expect("Invalid default key bindings")
+}
+
+fn parse_bindings(
+    path: &Path,
+    bindings: &BTreeMap<String, Vec<String>>,
+) -> Result<(KeyBindings, Vec<KeyConflict>), KeyBindingsError> {
+    let mut actions = HashMap::new();
+    let mut action_keys: HashMap<Action, Vec<&str>> = HashMap::new();
+    let mut bound_to: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+
+    for (action_name, key_names) in bindings {
+        let action = ACTION_NAMES
+            .iter()
+            .find(|(name, _action)| name == action_name)
+            .map(|(_name, action)| *action)
+            .ok_or_else(|| KeyBindingsError::UnknownAction {
+                path: path.to_path_buf(),
+                name: action_name.clone(),
+            })?;
+
+        for key_name in key_names {
+            let (name, key) = KEY_NAMES
+                .iter()
+                .find(|(name, _key)| name.eq_ignore_ascii_case(key_name))
+                .ok_or_else(|| KeyBindingsError::UnknownKey {
+                    path: path.to_path_buf(),
+                    name: key_name.clone(),
+                })?;
+
+            if !actions.contains_key(key) {
+                actions.insert(*key, action);
+                action_keys.entry(action).or_default().push(*name);
+            }
+            bound_to.entry(name).or_default().push(action_name.clone());
+        }
+    }
+
+    let conflicts = bound_to
+        .into_iter()
+        .filter(|(_key, actions)| actions.len() > 1)
+        .map(|(key, actions)| KeyConflict {
+            key: key.to_string(),
+            actions,
+        })
+        .collect();
+
+    Ok((
+        KeyBindings {
+            actions,
+            key_names: action_keys,
+        },
+        conflicts,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(content: &str) -> Result<(KeyBindings, Vec<KeyConflict>), KeyBindingsError> {
+        parse_user_bindings(Path::new("keybindings.toml"), content)
+    }
+
+    #[test]
+    fn keys_bound_twice_are_reported() {
+        let (key_bindings, conflicts) = parse("undo = [\"Z\"]\nredo = [\"Z\"]").unwrap();
+
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].key, "Z");
+        assert_eq!(conflicts[0].actions, vec!["redo", "undo"]);
+        assert_eq!(key_bindings.action(KeyCode::Z), Some(Action::Redo));
+    }
+
+    #[test]
+    fn left_out_actions_keep_their_default_keys() {
+        let (key_bindings, conflicts) = parse("undo = [\"U\"]").unwrap();
+
+        assert!(conflicts.is_empty());
+        assert_eq!(key_bindings.action(KeyCode::U), Some(Action::Undo));
+        assert_eq!(key_bindings.action(KeyCode::Z), None);
+        assert_eq!(key_bindings.action(KeyCode::R), Some(Action::Restart));
+        assert_eq!(
+            key_bindings.action(KeyCode::Up),
+            Some(Action::Move(Direction::Up))
+        );
+    }
+
+    #[test]
+    fn a_user_file_can_take_a_default_key() {
+        let (key_bindings, conflicts) = parse("move_up = [\"R\"]").unwrap();
+
+        assert!(conflicts.is_empty());
+        assert_eq!(
+            key_bindings.action(KeyCode::R),
+            Some(Action::Move(Direction::Up))
+        );
+        assert_eq!(key_bindings.key_name(Action::Restart), None);
+    }
+
+    #[test]
+    fn key_names_ignore_case() {
+        let (key_bindings, _conflicts) = parse("undo = [\"back\", \"u\"]").unwrap();
+
+        assert_eq!(key_bindings.action(KeyCode::Back), Some(Action::Undo));
+        assert_eq!(key_bindings.key_name(Action::Undo), Some("Back"));
+    }
+
+    #[test]
+    fn rejects_unknown_names() {
+        assert!(matches!(
+            parse("fly = [\"F\"]"),
+            Err(KeyBindingsError::UnknownAction { name, .. }) if name == "fly"
+        ));
+        assert!(matches!(
+            parse("undo = [\"Hyper\"]"),
+            Err(KeyBindingsError::UnknownKey { name, .. }) if name == "Hyper"
+        ));
+    }
+}
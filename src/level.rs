@@ -0,0 +1,151 @@
+use crate::board::Board;
+use crate::deadlock::DeadSquares;
+use crate::hint::HintSearch;
+use crate::map::load_map;
+use crate::resources::{
+    Camera, Deadlocks, EventQueue, Gameplay, GameplayState, Hint, InputQueue, LevelPack, MapSize,
+    MoveHistory, PushRule, Replay,
+};
+use crate::settings::Settings;
+use specs::{World, WorldExt};
+
+pub fn initialize_level(world: &mut World, name: String, levels: Vec<String>, level: usize) {
+    world.insert(LevelPack {
+        name,
+        levels,
+        current: level,
+        ..LevelPack::default()
+    });
+    load_level(world);
+}
+
+/// Loads the current level of the pack from scratch, also used to restart it
+pub fn load_level(world: &mut World) {
+    // Every entity belongs to the level, so the whole world can be cleared
+    world.delete_all();
+
+    *world.write_resource::<Gameplay>() = Gameplay::default();
+    *world.write_resource::<EventQueue>() = EventQueue::default();
+    *world.write_resource::<InputQueue>() = InputQueue::default();
+    *world.write_resource::<MoveHistory>() = MoveHistory::default();
+    *world.write_resource::<Hint>() = Hint::default();
+    world.write_resource::<HintSearch>().cancel();
+    *world.write_resource::<Deadlocks>() = Deadlocks::default();
+    world.write_resource::<Replay>().rewind();
+
+    // The level can override the push rule while it is being loaded
+    let push_rule = world.read_resource::<Settings>().push_rule;
+    *world.write_resource::<PushRule>() = push_rule;
+
+    let map = world
+        .read_resource::<LevelPack>()
+        .current_map()
+        .map(|x| x.to_string());
+    let load_error = match map {
+        Some(map) => load_map(world, &map).err().map(|error| error.to_string()),
+        None => None,
+    };
+    world.write_resource::<LevelPack>().load_error = load_error;
+
+    let dead_squares = Board::from_world(world)
+        .map(|board| DeadSquares::new(&board))
+        .unwrap_or_default();
+    *world.write_resource::<DeadSquares>() = dead_squares;
+
+    let map_size = world.read_resource::<MapSize>();
+    world.write_resource::<Camera>().fit(&map_size);
+}
+
+pub fn next_level(world: &mut World) {
+    {
+        let mut level_pack = world.write_resource::<LevelPack>();
+        if level_pack.is_complete() {
+            return;
+        }
+        level_pack.current += 1;
+    }
+
+    // A replay belongs to the level it was loaded for
+    *world.write_resource::<Replay>() = Replay::default();
+    load_level(world);
+}
+
+/// True when the current level has just been solved
+pub fn is_level_won(world: &World) -> bool {
+    // Rejected levels and the end of the pack have an empty board
+    let is_won = matches!(world.read_resource::<Gameplay>().state, GameplayState::Won);
+    let level_pack = world.read_resource::<LevelPack>();
+    let has_level = level_pack.load_error.is_none() && !level_pack.is_complete();
+
+    is_won && has_level
+}
+
+/// How a won level was played
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LevelScore {
+    // 0 based, as LevelPack::current
+    pub level: usize,
+    pub moves_count: u32,
+    pub pushes_count: u32,
+}
+
+/// Adds the level score to the pack totals and moves on to the next level
+pub fn finish_level(world: &mut World) -> LevelScore {
+    let score = {
+        let gameplay = world.read_resource::<Gameplay>();
+        let mut level_pack = world.write_resource::<LevelPack>();
+        level_pack.total_moves += gameplay.moves_count;
+        level_pack.total_pushes += gameplay.pushes_count;
+
+        LevelScore {
+            level: level_pack.current,
+            moves_count: gameplay.moves_count,
+            pushes_count: gameplay.pushes_count,
+        }
+    };
+
+    next_level(world);
+    score
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{play, RIGHT};
+    use crate::{create_world, run_gameplay};
+
+    #[test]
+    fn the_end_of_the_pack_is_not_won_again() {
+        let mut world = create_world();
+        let levels = vec![String::from("#######\n#@$ .##\n#######")];
+        initialize_level(&mut world, String::from("test"), levels, 0);
+
+        play(&mut world, &[RIGHT, RIGHT]);
+        assert!(is_level_won(&world));
+
+        let score = finish_level(&mut world);
+        assert_eq!(
+            score,
+            LevelScore {
+                level: 0,
+                moves_count: 2,
+                pushes_count: 2
+            }
+        );
+        assert_eq!(world.read_resource::<LevelPack>().total_moves, 2);
+
+        run_gameplay(&world);
+        assert!(world.read_resource::<LevelPack>().is_complete());
+        assert!(!is_level_won(&world));
+    }
+
+    #[test]
+    fn rejected_levels_are_never_won() {
+        let mut world = create_world();
+        let levels = vec![String::from("#####\n#$ .#\n#####")];
+        initialize_level(&mut world, String::from("test"), levels, 0);
+
+        play(&mut world, &[RIGHT]);
+        assert!(!is_level_won(&world));
+    }
+}
@@ -0,0 +1,189 @@
+//! Game rules of Rust Sokoban, usable without a window, GPU or audio device.
+//! Rendering and sound come with the default `ggez` feature, the binary adds
+//! the event loop on top of them.
+
+pub mod actions;
+#[cfg(feature = "ggez")]
+pub mod audio;
+pub mod board;
+pub mod components;
+pub mod constants;
+pub mod deadlock;
+pub mod entities;
+pub mod events;
+pub mod hint;
+#[cfg(feature = "ggez")]
+pub mod images;
+#[cfg(feature = "ggez")]
+pub mod keybindings;
+pub mod level;
+pub mod map;
+pub mod resources;
+pub mod settings;
+pub mod solution;
+pub mod solver;
+pub mod systems;
+pub mod theme;
+
+#[cfg(test)]
+mod testing;
+
+use crate::actions::Action;
+use crate::components::register_components;
+use crate::constants::MAX_BUFFERED_ACTIONS;
+use crate::hint::show_hint;
+use crate::level::{load_level, next_level};
+use crate::resources::{
+    register_resources, Camera, CameraMode, DebugOverlay, InputQueue, MapSize, Replay,
+};
+use crate::systems::{
+    AnimationSystem, CameraSystem, EventSystem, GameplayStateSystem, HintSystem, InputSystem,
+    PlayerSpriteSystem, ReplaySystem,
+};
+use crate::theme::next_theme;
+use specs::{RunNow, World, WorldExt};
+
+/// A world with every component and resource registered and no level loaded
+pub fn create_world() -> World {
+    let mut world = World::new();
+    register_components(&mut world);
+    register_resources(&mut world);
+
+    world
+}
+
+/// Applies an action from the keyboard, an agent or a test.
+/// Level changes happen right away, the rest is queued for the InputSystem.
+pub fn perform_action(world: &mut World, action: Action) {
+    {
+        // A replay only listens to its own controls until it is done
+        let mut replay = world.write_resource::<Replay>();
+        if replay.is_active() {
+            let is_replay_action = matches!(
+                action,
+                Action::ToggleReplayPause | Action::StepReplay | Action::ToggleReplayFastForward
+            );
+            match action {
+                Action::ToggleReplayPause => replay.paused = !replay.paused,
+                Action::StepReplay => replay.step_requested = true,
+                Action::ToggleReplayFastForward => replay.fast_forward = !replay.fast_forward,
+                _ => (),
+            }
+
+            // Looking around doesn't change the level
+            let is_view_action = matches!(
+                action,
+                Action::ToggleDeadSquares
+                    | Action::PanCamera(_)
+                    | Action::ZoomIn
+                    | Action::ZoomOut
+                    | Action::ToggleCameraFollow
+                    | Action::FitCamera
+                    | Action::NextTheme
+            );
+            if replay.is_finished() {
+                // A replay that stopped short of winning hands the level to the player
+                if !is_replay_action && !is_view_action {
+                    *replay = Replay::default();
+                }
+            } else if action != Action::Restart && !is_view_action {
+                return;
+            }
+        }
+    }
+
+    match action {
+        Action::Restart => load_level(world),
+        Action::NextLevel => next_level(world),
+        Action::Hint => show_hint(world),
+        Action::ToggleDeadSquares => {
+            let mut debug_overlay = world.write_resource::<DebugOverlay>();
+            debug_overlay.show_dead_squares = !debug_overlay.show_dead_squares;
+        }
+        Action::PanCamera(direction) => world.write_resource::<Camera>().pan(direction),
+        Action::ZoomIn => world.write_resource::<Camera>().zoom_in(),
+        Action::ZoomOut => world.write_resource::<Camera>().zoom_out(),
+        Action::ToggleCameraFollow => {
+            let map_size = world.read_resource::<MapSize>();
+            let mut camera = world.write_resource::<Camera>();
+            if camera.mode == CameraMode::Follow {
+                camera.fit(&map_size);
+            } else {
+                camera.mode = CameraMode::Follow;
+            }
+        }
+        Action::FitCamera => {
+            let map_size = world.read_resource::<MapSize>();
+            world.write_resource::<Camera>().fit(&map_size);
+        }
+        Action::NextTheme => next_theme(world),
+        Action::ToggleReplayPause | Action::StepReplay | Action::ToggleReplayFastForward => (),
+        action => {
+            let mut input_queue = world.write_resource::<InputQueue>();
+            if input_queue.actions.len() < MAX_BUFFERED_ACTIONS {
+                input_queue.actions.push_back(action);
+            }
+        }
+    }
+}
+
+/// Runs the gameplay systems once, rendering is left to the caller
+pub fn run_gameplay(world: &World) {
+    let mut replay_system = ReplaySystem {};
+    replay_system.run_now(world);
+
+    let mut input_system = InputSystem {};
+    input_system.run_now(world);
+
+    let mut hint_system = HintSystem {};
+    hint_system.run_now(world);
+
+    let mut gameplay_state_system = GameplayStateSystem {};
+    gameplay_state_system.run_now(world);
+
+    let mut event_system = EventSystem {};
+    event_system.run_now(world);
+
+    let mut player_sprite_system = PlayerSpriteSystem {};
+    player_sprite_system.run_now(world);
+
+    let mut animation_system = AnimationSystem {};
+    animation_system.run_now(world);
+
+    let mut camera_system = CameraSystem {};
+    camera_system.run_now(world);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actions::Direction;
+    use crate::components::Player;
+    use crate::testing::{load, play, positions, DOWN};
+
+    fn start_replay(steps: Vec<Direction>) -> World {
+        let world = load("######\n#@$ .#\n#    #\n######");
+        world.write_resource::<Replay>().steps = steps;
+        world
+    }
+
+    #[test]
+    fn a_running_replay_ignores_moves() {
+        let mut world = start_replay(vec![Direction::Right]);
+        world.write_resource::<Replay>().paused = true;
+        play(&mut world, &[DOWN]);
+
+        assert_eq!(positions::<Player>(&world), vec![(1, 1)]);
+    }
+
+    #[test]
+    fn a_finished_replay_hands_back_control() {
+        let mut world = start_replay(vec![Direction::Right]);
+        play(&mut world, &[Action::ToggleReplayFastForward]);
+        assert!(world.read_resource::<Replay>().is_finished());
+
+        play(&mut world, &[DOWN]);
+        assert!(!world.read_resource::<Replay>().is_active());
+        assert_eq!(positions::<Player>(&world), vec![(2, 2)]);
+    }
+}
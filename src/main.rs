@@ -1,27 +1,83 @@
-use audio::initialize_sounds;
-use ggez::{conf, event, timer, Context, ContextBuilder, GameResult};
+use ggez::{
+    conf, event, filesystem, graphics, timer, Context, ContextBuilder, GameError, GameResult,
+};
+use rust_sokoban::audio::{initialize_sounds, play_sounds};
+use rust_sokoban::constants::{
+    DEFAULT_LEVEL_PACK_PATH, KEY_BINDINGS_FILE, RESOURCES_PATH, SETTINGS_PATH, SOLUTIONS_PATH,
+    THEMES_PATH, THEME_FILE, WINDOW_HEIGHT, WINDOW_WIDTH,
+};
+use rust_sokoban::images::ImageCache;
+use rust_sokoban::keybindings::{load_key_bindings, KeyBindings};
+use rust_sokoban::level::{finish_level, initialize_level, is_level_won, load_level};
+use rust_sokoban::map::read_level_pack;
+use rust_sokoban::resources::{Camera, Gameplay, LevelPack, MapSize, MoveHistory, Replay, Time};
+use rust_sokoban::settings::{load_settings, Scaling};
+use rust_sokoban::solution::{parse_lurd, read_solution, verify_solution, write_solution};
+use rust_sokoban::solver::{solve_world, SolverLimits};
+use rust_sokoban::systems::RenderingSystem;
+use rust_sokoban::theme::load_theme;
+use rust_sokoban::{create_world, perform_action, run_gameplay};
 use specs::{RunNow, World, WorldExt};
-use systems::EventSystem;
-
-mod audio;
-mod components;
-mod constants;
-mod entities;
-mod events;
-mod map;
-mod resources;
-mod systems;
-
-use crate::components::register_components;
-use crate::map::load_map;
-use crate::resources::{register_resources, InputQueue, Time};
-use crate::systems::{GameplayStateSystem, InputSystem, RenderingSystem};
-use std::path;
+use std::{env, path, process};
 
 struct Game {
     world: World,
 }
 
+#[derive(Default)]
+struct Options {
+    pack_path: Option<path::PathBuf>,
+    // 0 based, the command line takes it from 1
+    level: Option<usize>,
+    verify_path: Option<path::PathBuf>,
+    solve: bool,
+    // a solution file or a LURD string
+    replay: Option<String>,
+    replay_speed: Option<f32>,
+}
+
+const USAGE: &str = "Usage: rust-sokoban [PACK] [--level N] [--verify SOLUTION] [--solve] \
+                     [--replay SOLUTION|LURD] [--replay-speed STEPS_PER_SECOND]";
+
+fn parse_options() -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut args = env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--level" => {
+                let level = args
+                    .next()
+                    .and_then(|level| level.parse::<usize>().ok())
+                    .filter(|level| *level > 0)
+                    .ok_or_else(|| USAGE.to_string())?;
+                options.level = Some(level - 1);
+            }
+            "--verify" => {
+                options.verify_path = Some(args.next().ok_or_else(|| USAGE.to_string())?.into());
+            }
+            "--solve" => options.solve = true,
+            "--replay" => {
+                options.replay = Some(args.next().ok_or_else(|| USAGE.to_string())?);
+            }
+            "--replay-speed" => {
+                options.replay_speed = Some(
+                    args.next()
+                        .and_then(|speed| speed.parse::<f32>().ok())
+                        .filter(|speed| *speed > 0.0)
+                        .ok_or_else(|| USAGE.to_string())?,
+                );
+            }
+            _ if options.pack_path.is_none() && !arg.starts_with("--") => {
+                options.pack_path = Some(arg.into());
+            }
+            _ => return Err(USAGE.to_string()),
+        }
+    }
+
+    Ok(options)
+}
+
 impl event::EventHandler for Game {
     fn key_down_event(
         &mut self,
@@ -30,22 +86,35 @@ impl event::EventHandler for Game {
         _keymods: event::KeyMods,
         _repeat: bool,
     ) {
-        let mut input_queue = self.world.write_resource::<InputQueue>();
-        input_queue.keys_pressed.push(keycode);
+        let action = self.world.read_resource::<KeyBindings>().action(keycode);
+        if let Some(action) = action {
+            perform_action(&mut self.world, action);
+        }
     }
 
-    fn update(&mut self, context: &mut Context) -> GameResult {
-        let mut input_system = InputSystem {};
-        input_system.run_now(&self.world);
+    fn resize_event(&mut self, context: &mut Context, width: f32, height: f32) {
+        resize(&self.world, context, width, height);
+    }
 
-        let mut gameplay_state_system = GameplayStateSystem {};
-        gameplay_state_system.run_now(&self.world);
+    fn update(&mut self, context: &mut Context) -> GameResult {
+        run_gameplay(&self.world);
+        play_sounds(&self.world);
 
-        let mut time = self.world.write_resource::<Time>();
-        time.delta += timer::delta(context);
+        {
+            let mut time = self.world.write_resource::<Time>();
+            time.delta += timer::delta(context);
+        }
 
-        let mut event_system = EventSystem {};
-        event_system.run_now(&self.world);
+        if is_level_won(&self.world) {
+            export_solution(&self.world);
+            let score = finish_level(&mut self.world);
+            println!(
+                "Level {} won in {} moves, {} pushes",
+                score.level + 1,
+                score.moves_count,
+                score.pushes_count
+            );
+        }
 
         Ok(())
     }
@@ -57,35 +126,203 @@ impl event::EventHandler for Game {
     }
 }
 
-fn initialize_level(world: &mut World) {
-    let map = "
-    N N W W W W W W
-    W W W . . . . W
-    W . . . BB . . W
-    W . . RB . . . W 
-    W . P . . W . W
-    W . . . . RS . W
-    W . . BS . . . W
-    W . . . . . . W
-    W W W W W W W W
-    ";
-
-    load_map(world, map);
+// Draws in window pixels, the camera takes care of the level's scale
+fn resize(world: &World, context: &mut Context, width: f32, height: f32) {
+    graphics::set_screen_coordinates(context, graphics::Rect::new(0.0, 0.0, width, height))
+        .expect("Resize error");
+
+    let map_size = world.read_resource::<MapSize>();
+    world
+        .write_resource::<Camera>()
+        .resize((width, height), &map_size);
+}
+
+fn export_solution(world: &World) {
+    let lurd = world.read_resource::<MoveHistory>().lurd();
+    let level_pack = world.read_resource::<LevelPack>();
+    let path = path::Path::new(SOLUTIONS_PATH).join(format!(
+        "{}_{:03}.sol",
+        level_pack.name,
+        level_pack.current + 1
+    ));
+
+    println!("Solution: {}", lurd);
+    match write_solution(&path, &lurd) {
+        Ok(()) => println!("Solution saved to {}", path.display()),
+        Err(error) => eprintln!("Could not save solution {}: {}", path.display(), error),
+    }
+}
+
+fn solve_levels(world: &mut World, level: Option<usize>) -> bool {
+    let levels = match level {
+        Some(level) => vec![level],
+        None => (0..world.read_resource::<LevelPack>().levels.len()).collect(),
+    };
+    let limits = SolverLimits::default();
+    let mut is_solved = true;
+
+    for level in levels {
+        world.write_resource::<LevelPack>().current = level;
+        load_level(world);
+
+        let load_error = world.read_resource::<LevelPack>().load_error.clone();
+        let result = match load_error {
+            Some(load_error) => Err(load_error),
+            None => solve_world(world, &limits).map_err(|error| error.to_string()),
+        };
+
+        match result {
+            Ok(lurd) => {
+                let pushes = lurd.chars().filter(|x| x.is_ascii_uppercase()).count();
+                println!("Level {}: {} ({} pushes)", level + 1, lurd, pushes);
+            }
+            Err(error) => {
+                println!("Level {}: {}", level + 1, error);
+                is_solved = false;
+            }
+        }
+    }
+
+    is_solved
 }
 
 fn main() -> GameResult {
-    let mut world = World::new();
-    register_components(&mut world);
-    register_resources(&mut world);
-    initialize_level(&mut world);
+    let mut world = create_world();
+
+    let settings = load_settings(path::Path::new(SETTINGS_PATH))
+        .map_err(|error| GameError::ConfigError(error.to_string()))?;
+    world.write_resource::<Camera>().scaling = settings.scaling;
+    let fullscreen = settings.fullscreen;
+    let theme_name = settings.theme.clone();
+    world.insert(settings);
+
+    let options = parse_options().map_err(GameError::ConfigError)?;
+
+    // A level file or a pack file / directory can be given as the first argument
+    let pack_path = options
+        .pack_path
+        .unwrap_or_else(|| path::PathBuf::from(DEFAULT_LEVEL_PACK_PATH));
+    let pack_name = pack_path.file_stem().map_or_else(
+        || String::from("level"),
+        |x| x.to_string_lossy().into_owned(),
+    );
+
+    // A pack directory can bring its own theme, otherwise the settings pick one
+    let pack_theme_path = pack_path.join(THEME_FILE);
+    let theme_path = if pack_theme_path.is_file() {
+        pack_theme_path
+    } else {
+        path::Path::new(THEMES_PATH).join(format!("{}.toml", theme_name))
+    };
+    match load_theme(&theme_path) {
+        Ok(theme) => world.insert(theme),
+        Err(error) => eprintln!("{}, using the default theme", error),
+    }
+
+    let levels = read_level_pack(&pack_path)
+        .map_err(|error| GameError::ResourceLoadError(error.to_string()))?;
+    let level = options.level.unwrap_or(0);
+    if level >= levels.len() {
+        return Err(GameError::ConfigError(format!(
+            "There is no level {}, the pack has {} levels",
+            level + 1,
+            levels.len()
+        )));
+    }
+    initialize_level(&mut world, pack_name, levels, level);
+
+    // Solving needs no window, the whole pack is checked unless a level was picked
+    if options.solve {
+        let is_solved = solve_levels(&mut world, options.level);
+        process::exit(if is_solved { 0 } else { 1 });
+    }
 
+    if let Some(replay) = options.replay {
+        // Anything that is not a file is taken as the LURD string itself
+        let lurd = read_solution(path::Path::new(&replay)).unwrap_or(replay);
+        let steps = parse_lurd(&lurd)
+            .map_err(|error| GameError::ConfigError(format!("Invalid replay: {}", error)))?;
+
+        let mut replay = world.write_resource::<Replay>();
+        replay.steps = steps
+            .into_iter()
+            .map(|(direction, _is_push)| direction)
+            .collect();
+        if let Some(speed) = options.replay_speed {
+            replay.speed = speed;
+        }
+    }
+
+    // Checking a solution needs no window
+    if let Some(verify_path) = options.verify_path {
+        if let Some(load_error) = &world.read_resource::<LevelPack>().load_error {
+            eprintln!("{}", load_error);
+            process::exit(1);
+        }
+
+        let lurd = read_solution(&verify_path).map_err(|error| {
+            GameError::ResourceLoadError(format!(
+                "Could not read solution {}: {}",
+                verify_path.display(),
+                error
+            ))
+        })?;
+
+        match verify_solution(&world, &lurd) {
+            Ok(()) => {
+                let gameplay = world.read_resource::<Gameplay>();
+                println!(
+                    "Solution is valid: {} moves, {} pushes",
+                    gameplay.moves_count, gameplay.pushes_count
+                );
+                process::exit(0);
+            }
+            Err(error) => {
+                eprintln!("Solution is invalid: {}", error);
+                process::exit(1);
+            }
+        }
+    }
+
+    let fullscreen_type = if fullscreen {
+        conf::FullscreenType::Desktop
+    } else {
+        conf::FullscreenType::Windowed
+    };
     let context_builder = ContextBuilder::new("rust_sokoban", "zenoplex")
         .window_setup(conf::WindowSetup::default().title("Rust Sokoban"))
-        .window_mode(conf::WindowMode::default().dimensions(800.0, 600.0))
-        .add_resource_path(path::PathBuf::from("./resources"));
+        .window_mode(
+            conf::WindowMode::default()
+                .dimensions(WINDOW_WIDTH, WINDOW_HEIGHT)
+                .resizable(true)
+                .fullscreen_type(fullscreen_type),
+        )
+        .add_resource_path(path::PathBuf::from(RESOURCES_PATH));
 
     let (mut context, mut event_loop) = context_builder.build()?;
+
+    // Fullscreen windows don't get the size that was asked for
+    let (width, height) = graphics::drawable_size(&context);
+    resize(&world, &mut context, width, height);
+    let filter_mode = match world.read_resource::<Camera>().scaling {
+        Scaling::Integer => graphics::FilterMode::Nearest,
+        Scaling::Filtered => graphics::FilterMode::Linear,
+    };
+    graphics::set_default_filter(&mut context, filter_mode);
     initialize_sounds(&mut world, &mut context);
+    world.insert(ImageCache::default());
+
+    // The user's own bindings win over the ones shipped with the game
+    let key_bindings_paths = [
+        filesystem::user_config_dir(&context).join(KEY_BINDINGS_FILE),
+        path::Path::new(RESOURCES_PATH).join(KEY_BINDINGS_FILE),
+    ];
+    let (key_bindings, conflicts) = load_key_bindings(&key_bindings_paths)
+        .map_err(|error| GameError::ConfigError(error.to_string()))?;
+    for conflict in conflicts {
+        eprintln!("{}", conflict);
+    }
+    world.insert(key_bindings);
 
     let game = &mut Game { world };
     event::run(&mut context, &mut event_loop, game)
@@ -1,53 +1,607 @@
 use crate::components::{BoxColor, Position};
 use crate::entities::*;
-use specs::World;
+use crate::resources::{MapSize, PushRule};
+use itertools::Itertools;
+use specs::{World, WorldExt};
+use std::collections::HashSet;
+use std::fmt;
+use std::fmt::Display;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
 
-pub fn load_map(world: &mut World, map_string: &str) {
-    let rows: Vec<&str> = map_string.trim().split('\n').map(|x| x.trim()).collect();
+// Positions are stored as u8, the last index is kept free so moves never overflow
+const MAX_MAP_SIZE: usize = u8::MAX as usize;
 
-    for (y, row) in rows.iter().enumerate() {
-        let columns: Vec<&str> = row.split(' ').collect();
+// Files with other extensions in a level directory are ignored
+const LEVEL_EXTENSIONS: [&str; 2] = ["txt", "xsb"];
 
-        for (x, column) in columns.iter().enumerate() {
+#[derive(Debug)]
+pub enum MapError {
+    Io {
+        path: PathBuf,
+        error: io::Error,
+    },
+    EmptyPack {
+        path: PathBuf,
+    },
+    TooLarge {
+        width: usize,
+        height: usize,
+    },
+    // rows and columns are board coordinates, starting at 0
+    UnrecognizedItem {
+        row: usize,
+        column: usize,
+        token: String,
+    },
+    InvalidPushRule {
+        value: String,
+    },
+    MissingPlayer,
+    MultiplePlayers {
+        count: usize,
+    },
+    BoxSpotMismatch {
+        color: BoxColor,
+        boxes: usize,
+        spots: usize,
+    },
+    UnclosedWalls {
+        row: usize,
+        column: usize,
+    },
+}
+
+impl Display for MapError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MapError::Io { path, error } => {
+                write!(fmt, "Could not read level {}: {}", path.display(), error)
+            }
+            MapError::EmptyPack { path } => {
+                write!(fmt, "No levels found in {}", path.display())
+            }
+            MapError::TooLarge { width, height } => write!(
+                fmt,
+                "The level is {}x{}, levels can be at most {}x{}",
+                width, height, MAX_MAP_SIZE, MAX_MAP_SIZE
+            ),
+            MapError::UnrecognizedItem { row, column, token } => write!(
+                fmt,
+                "Unrecognized map item {:?} at row {}, column {}",
+                token, row, column
+            ),
+            MapError::InvalidPushRule { value } => write!(
+                fmt,
+                "Unknown push rule {:?}, expected classic or chain",
+                value
+            ),
+            MapError::MissingPlayer => fmt.write_str("The level has no player"),
+            MapError::MultiplePlayers { count } => {
+                write!(fmt, "The level has {} players instead of one", count)
+            }
+            MapError::BoxSpotMismatch {
+                color,
+                boxes,
+                spots,
+            } => write!(
+                fmt,
+                "The level has {} {} boxes but {} {} spots",
+                boxes, color, spots, color
+            ),
+            MapError::UnclosedWalls { row, column } => write!(
+                fmt,
+                "The walls are not closed, the player can leave at row {}, column {}",
+                row, column
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MapError {}
+
+fn read_map_file(path: &Path) -> Result<String, MapError> {
+    fs::read_to_string(path).map_err(|error| MapError::Io {
+        path: path.to_path_buf(),
+        error,
+    })
+}
+
+/// Reads every level of a pack, which is either a single file with levels
+/// separated by blank lines or a directory of such files in name order,
+/// numbers in the names are compared by value
+pub fn read_level_pack(path: &Path) -> Result<Vec<String>, MapError> {
+    let paths = if path.is_dir() {
+        let entries = fs::read_dir(path).map_err(|error| MapError::Io {
+            path: path.to_path_buf(),
+            error,
+        })?;
+
+        let mut paths: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                let extension = path.extension().and_then(|extension| extension.to_str());
+                matches!(extension, Some(extension) if LEVEL_EXTENSIONS.contains(&extension))
+            })
+            .collect();
+        paths.sort_by_cached_key(|path| natural_key(path));
+        paths
+    } else {
+        vec![path.to_path_buf()]
+    };
+
+    let mut levels = Vec::new();
+    for path in paths {
+        levels.extend(split_levels(&read_map_file(&path)?));
+    }
+
+    if levels.is_empty() {
+        return Err(MapError::EmptyPack {
+            path: path.to_path_buf(),
+        });
+    }
+
+    Ok(levels)
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum NameRun {
+    Number(u64),
+    Text(String),
+}
+
+// The file name split into runs of digits and other characters, so level_2 comes before level_10
+fn natural_key(path: &Path) -> Vec<NameRun> {
+    let name = path
+        .file_name()
+        .map_or_else(String::new, |x| x.to_string_lossy().into_owned());
+
+    name.chars()
+        .group_by(|x| x.is_ascii_digit())
+        .into_iter()
+        .map(|(is_number, run)| {
+            let run: String = run.collect();
+            if is_number {
+                NameRun::Number(run.parse().unwrap_or(u64::MAX))
+            } else {
+                NameRun::Text(run)
+            }
+        })
+        .collect()
+}
+
+fn split_levels(pack: &str) -> Vec<String> {
+    let mut levels = Vec::new();
+    let mut level = String::new();
+
+    for line in pack.lines().chain(std::iter::once("")) {
+        if !line.trim().is_empty() {
+            level.push_str(line);
+            level.push('\n');
+            continue;
+        }
+
+        // Chunks made only of titles or comments are not levels
+        if level.lines().any(is_board_row) {
+            levels.push(level.clone());
+        }
+        level.clear();
+    }
+
+    levels
+}
+
+// Comments start with `;` and metadata lines look like `Title: ...`
+fn is_board_row(row: &str) -> bool {
+    let row = row.trim();
+    !row.is_empty() && !row.starts_with(';') && !row.contains(':')
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum MapItem {
+    Wall,
+    Player,
+    Box(BoxColor),
+    BoxSpot(BoxColor),
+}
+
+// A parsed cell, None is outside of the level and gets no floor
+type Tile = Option<Vec<MapItem>>;
+
+pub fn load_map(world: &mut World, map_string: &str) -> Result<(), MapError> {
+    // Walls are always `#` in XSB and never appear in our own format,
+    // titles and comments can contain anything
+    let is_xsb = map_string
+        .lines()
+        .any(|row| is_board_row(row) && row.contains('#'));
+    let tiles = if is_xsb {
+        parse_xsb_map(map_string)?
+    } else {
+        parse_map(map_string)?
+    };
+
+    // Nothing is created until the whole level is known to be valid
+    validate_map(&tiles)?;
+
+    if let Some(value) = read_metadata(map_string, "PushRule") {
+        let push_rule = value
+            .parse::<PushRule>()
+            .map_err(|_| MapError::InvalidPushRule {
+                value: value.to_string(),
+            })?;
+        *world.write_resource::<PushRule>() = push_rule;
+    }
+
+    let width = tiles.iter().map(|row| row.len()).max().unwrap_or(0);
+    let height = tiles.len();
+    *world.write_resource::<MapSize>() = MapSize {
+        width: width as u8,
+        height: height as u8,
+    };
+
+    for (y, row) in tiles.into_iter().enumerate() {
+        for (x, tile) in row.into_iter().enumerate() {
             let position = Position {
                 x: x as u8,
                 y: y as u8,
                 z: 0,
             };
 
-            match *column {
-                "." => {
-                    create_floor(world, position);
-                }
-                "W" => {
-                    create_floor(world, position);
-                    create_wall(world, position);
-                }
-                "P" => {
-                    create_floor(world, position);
-                    create_player(world, position);
-                }
-                "RB" => {
-                    create_floor(world, position);
-                    create_box(world, position, BoxColor::Red);
-                }
-                "BB" => {
-                    create_floor(world, position);
-                    create_box(world, position, BoxColor::Blue);
-                }
-                "RS" => {
-                    create_floor(world, position);
-                    create_box_spot(world, position, BoxColor::Red);
+            if let Some(items) = tile {
+                create_floor(world, position);
+
+                for item in items {
+                    match item {
+                        MapItem::Wall => create_wall(world, position),
+                        MapItem::Player => create_player(world, position),
+                        MapItem::Box(color) => create_box(world, position, color),
+                        MapItem::BoxSpot(color) => create_box_spot(world, position, color),
+                    };
                 }
-                "BS" => {
-                    create_floor(world, position);
-                    create_box_spot(world, position, BoxColor::Blue);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Finds the value of a `Key: value` line of the level
+fn read_metadata<'a>(map_string: &'a str, key: &str) -> Option<&'a str> {
+    map_string.lines().find_map(|line| {
+        let (line_key, value) = line.split_at(line.find(':')?);
+        if line_key.trim().eq_ignore_ascii_case(key) {
+            Some(value[1..].trim())
+        } else {
+            None
+        }
+    })
+}
+
+fn parse_map(map_string: &str) -> Result<Vec<Vec<Tile>>, MapError> {
+    let rows: Vec<&str> = map_string
+        .lines()
+        .map(|x| x.trim())
+        .filter(|x| is_board_row(x))
+        .collect();
+
+    rows.iter()
+        .enumerate()
+        .map(|(row, columns)| {
+            columns
+                .split_whitespace()
+                .enumerate()
+                .map(|(column, token)| match token {
+                    "." => Ok(Some(vec![])),
+                    "W" => Ok(Some(vec![MapItem::Wall])),
+                    "P" => Ok(Some(vec![MapItem::Player])),
+                    "RB" => Ok(Some(vec![MapItem::Box(BoxColor::Red)])),
+                    "BB" => Ok(Some(vec![MapItem::Box(BoxColor::Blue)])),
+                    "RS" => Ok(Some(vec![MapItem::BoxSpot(BoxColor::Red)])),
+                    "BS" => Ok(Some(vec![MapItem::BoxSpot(BoxColor::Blue)])),
+                    "N" => Ok(None),
+                    token => Err(MapError::UnrecognizedItem {
+                        row,
+                        column,
+                        token: token.to_string(),
+                    }),
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn parse_xsb_map(map_string: &str) -> Result<Vec<Vec<Tile>>, MapError> {
+    let color = BoxColor::default();
+
+    // Every board row has a wall, anything else is a title or a comment
+    let rows: Vec<&str> = map_string
+        .lines()
+        .map(|x| x.trim_end())
+        .filter(|x| is_board_row(x) && x.contains('#'))
+        .collect();
+
+    let mut tiles: Vec<Vec<Tile>> = rows
+        .iter()
+        .enumerate()
+        .map(|(row, columns)| {
+            columns
+                .chars()
+                .enumerate()
+                .map(|(column, char)| match char {
+                    ' ' | '-' | '_' => Ok(None),
+                    '#' => Ok(Some(vec![MapItem::Wall])),
+                    '@' => Ok(Some(vec![MapItem::Player])),
+                    '+' => Ok(Some(vec![MapItem::BoxSpot(color), MapItem::Player])),
+                    '$' => Ok(Some(vec![MapItem::Box(color)])),
+                    '*' => Ok(Some(vec![MapItem::BoxSpot(color), MapItem::Box(color)])),
+                    '.' => Ok(Some(vec![MapItem::BoxSpot(color)])),
+                    char => Err(MapError::UnrecognizedItem {
+                        row,
+                        column,
+                        token: char.to_string(),
+                    }),
+                })
+                .collect()
+        })
+        .collect::<Result<_, _>>()?;
+
+    // XSB uses spaces both for floor and for the outside of the level,
+    // so only the empty cells the player can walk to get a floor
+    let mut stack = player_positions(&tiles);
+    let mut visited = HashSet::new();
+    while let Some((x, y)) = stack.pop() {
+        if y >= tiles.len() || x >= tiles[y].len() || !visited.insert((x, y)) {
+            continue;
+        }
+
+        let tile = &mut tiles[y][x];
+        match tile {
+            Some(items) if items.contains(&MapItem::Wall) => continue,
+            Some(_) => (),
+            None => *tile = Some(vec![]),
+        }
+
+        stack.push((x + 1, y));
+        stack.push((x, y + 1));
+        if x > 0 {
+            stack.push((x - 1, y));
+        }
+        if y > 0 {
+            stack.push((x, y - 1));
+        }
+    }
+
+    Ok(tiles)
+}
+
+fn player_positions(tiles: &[Vec<Tile>]) -> Vec<(usize, usize)> {
+    let mut positions = Vec::new();
+
+    for (y, row) in tiles.iter().enumerate() {
+        for (x, tile) in row.iter().enumerate() {
+            if let Some(items) = tile {
+                if items.contains(&MapItem::Player) {
+                    positions.push((x, y));
                 }
-                "N" => (),
-                char => panic!("Unrecognized map item {}", char),
             }
         }
     }
 
-    println!("{:?}", rows);
+    positions
+}
+
+fn validate_map(tiles: &[Vec<Tile>]) -> Result<(), MapError> {
+    let width = tiles.iter().map(|row| row.len()).max().unwrap_or(0);
+    let height = tiles.len();
+    if width > MAX_MAP_SIZE || height > MAX_MAP_SIZE {
+        return Err(MapError::TooLarge { width, height });
+    }
+
+    let players = player_positions(tiles);
+    let player = match players.len() {
+        0 => return Err(MapError::MissingPlayer),
+        1 => players[0],
+        count => return Err(MapError::MultiplePlayers { count }),
+    };
+
+    // (color, boxes, spots) in the order the colours first appear
+    let mut counts: Vec<(BoxColor, usize, usize)> = Vec::new();
+    for item in tiles.iter().flatten().flatten().flatten() {
+        let (color, is_box) = match item {
+            MapItem::Box(color) => (*color, true),
+            MapItem::BoxSpot(color) => (*color, false),
+            _ => continue,
+        };
+
+        let index = match counts.iter().position(|count| count.0 == color) {
+            Some(index) => index,
+            None => {
+                counts.push((color, 0, 0));
+                counts.len() - 1
+            }
+        };
+
+        if is_box {
+            counts[index].1 += 1;
+        } else {
+            counts[index].2 += 1;
+        }
+    }
+
+    if let Some((color, boxes, spots)) = counts.into_iter().find(|count| count.1 != count.2) {
+        return Err(MapError::BoxSpotMismatch {
+            color,
+            boxes,
+            spots,
+        });
+    }
+
+    // Walk from the player, reaching the edge or an empty cell means a gap in the walls
+    let is_inside = |x: usize, y: usize| y < tiles.len() && x < tiles[y].len();
+    let mut stack = vec![player];
+    let mut visited = HashSet::new();
+    while let Some((x, y)) = stack.pop() {
+        if !visited.insert((x, y)) {
+            continue;
+        }
+
+        match &tiles[y][x] {
+            Some(items) if items.contains(&MapItem::Wall) => continue,
+            Some(_) => (),
+            None => return Err(MapError::UnclosedWalls { row: y, column: x }),
+        }
+
+        // Rows can have different lengths, anything past their end is outside
+        let has_neighbors =
+            x > 0 && y > 0 && is_inside(x + 1, y) && is_inside(x, y + 1) && is_inside(x, y - 1);
+        if !has_neighbors {
+            return Err(MapError::UnclosedWalls { row: y, column: x });
+        }
+
+        stack.push((x + 1, y));
+        stack.push((x - 1, y));
+        stack.push((x, y + 1));
+        stack.push((x, y - 1));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::{Box, BoxSpot, Player};
+    use crate::create_world;
+    use crate::testing::{load, load_error, positions};
+    use specs::{Join, WorldExt};
+
+    #[test]
+    fn xsb_floor_stays_inside_the_walls() {
+        let tiles = parse_xsb_map("  ####\n###  #\n#@$ .#\n######").unwrap();
+
+        assert_eq!(tiles[0][0], None);
+        assert_eq!(tiles[1][3], Some(vec![]));
+        assert_eq!(tiles[2][2], Some(vec![MapItem::Box(BoxColor::Red)]));
+    }
+
+    #[test]
+    fn xsb_items_on_spots_get_both_entities() {
+        let world = load("; Two boxes\n#####\n#+*$#\n#####");
+
+        assert_eq!(positions::<Player>(&world), vec![(1, 1)]);
+        assert_eq!(positions::<Box>(&world), vec![(2, 1), (3, 1)]);
+        assert_eq!(positions::<BoxSpot>(&world), vec![(1, 1), (2, 1)]);
+
+        let boxes = world.read_storage::<Box>();
+        assert!(boxes.join().all(|box_| box_.color == BoxColor::default()));
+    }
+
+    #[test]
+    fn custom_levels_keep_their_colours() {
+        let world = load("W W W W W\nW P BB BS W\nW W W W W");
+
+        let boxes = world.read_storage::<Box>();
+        assert!(boxes.join().all(|box_| box_.color == BoxColor::Blue));
+    }
+
+    #[test]
+    fn the_format_comes_from_the_board_rows() {
+        let world = load("Title: Puzzle #3\n; #1 of the pack\nW W W W W\nW P BB BS W\nW W W W W");
+
+        assert_eq!(positions::<Box>(&world), vec![(2, 1)]);
+    }
+
+    #[test]
+    fn rejects_broken_levels() {
+        assert!(matches!(
+            load_error("#####\n#$ .#\n#####"),
+            MapError::MissingPlayer
+        ));
+        assert!(matches!(
+            load_error("#####\n#@@ #\n#####"),
+            MapError::MultiplePlayers { count: 2 }
+        ));
+        assert!(matches!(
+            load_error("W W W W W\nW P RB BS W\nW W W W W"),
+            MapError::BoxSpotMismatch {
+                color: BoxColor::Red,
+                boxes: 1,
+                spots: 0,
+            }
+        ));
+        assert!(matches!(
+            load_error("#####\n#@$.\n#####"),
+            MapError::UnclosedWalls { .. }
+        ));
+        assert!(matches!(
+            load_error("W W W W\nW P X W\nW W W W"),
+            MapError::UnrecognizedItem {
+                row: 1,
+                column: 2,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn records_the_map_size() {
+        let world = load("  ####\n###  #\n#@$ .#\n######");
+        let map_size = world.read_resource::<MapSize>();
+
+        assert_eq!((map_size.width, map_size.height), (6, 4));
+    }
+
+    #[test]
+    fn rejects_levels_too_large_for_positions() {
+        let too_wide = format!("#####\n#@$.{}\n#####", "#".repeat(300));
+
+        assert!(matches!(
+            load_error(&too_wide),
+            MapError::TooLarge { width: 304, .. }
+        ));
+    }
+
+    #[test]
+    fn levels_can_set_the_push_rule() {
+        let world = load("pushrule: Chain\n#####\n#@$.#\n#####");
+        assert_eq!(*world.read_resource::<PushRule>(), PushRule::Chain);
+
+        assert!(matches!(
+            load_error("PushRule: sideways\n#####\n#@$.#\n#####"),
+            MapError::InvalidPushRule { .. }
+        ));
+    }
+
+    #[test]
+    fn rejected_levels_create_nothing() {
+        let mut world = create_world();
+        assert!(load_map(&mut world, "#####\n#@$ #\n#####").is_err());
+
+        assert!(positions::<Player>(&world).is_empty());
+    }
+
+    #[test]
+    fn packs_split_at_blank_lines() {
+        let pack = "; Pack\n\nTitle: One\n####\n#@$.#\n\n\n####\n#.$@#\n; the end\n";
+        let levels = split_levels(pack);
+
+        assert_eq!(levels.len(), 2);
+        assert!(levels[0].starts_with("Title: One"));
+    }
+
+    #[test]
+    fn level_files_sort_by_number() {
+        let mut paths: Vec<PathBuf> = ["level_10.txt", "level_2.xsb", "level_1.txt", "intro.txt"]
+            .iter()
+            .map(PathBuf::from)
+            .collect();
+        paths.sort_by_cached_key(|path| natural_key(path));
+
+        let names: Vec<_> = paths.iter().map(|x| x.to_str().unwrap()).collect();
+        assert_eq!(
+            names,
+            vec!["intro.txt", "level_1.txt", "level_2.xsb", "level_10.txt"]
+        );
+    }
 }
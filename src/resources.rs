@@ -1,27 +1,37 @@
+use crate::actions::{Action, Direction};
+use crate::board::Cell;
+use crate::constants::{
+    HUD_WIDTH, MAX_ZOOM, MIN_ZOOM, REPLAY_FAST_FORWARD, REPLAY_SPEED, TILE_WIDTH, WINDOW_HEIGHT,
+    WINDOW_WIDTH, ZOOM_STEP,
+};
+use crate::deadlock::DeadSquares;
 use crate::events::Event;
-use crate::audio::AudioStore;
-use ggez::event;
+use crate::hint::HintSearch;
+use crate::settings::{Scaling, Settings};
+use crate::solution::lurd_char;
+use crate::theme::Theme;
+use serde::Deserialize;
+use specs::world::Index;
 use specs::World;
+use std::collections::{HashSet, VecDeque};
 use std::fmt;
 use std::fmt::Display;
+use std::str::FromStr;
 use std::time::Duration;
 
+/// Actions in the order they were given, they wait here while a move is animating
 #[derive(Default)]
 pub struct InputQueue {
-    pub keys_pressed: Vec<event::KeyCode>,
+    pub actions: VecDeque<Action>,
 }
 
+#[derive(Default)]
 pub enum GameplayState {
+    #[default]
     Playing,
     Won,
 }
 
-impl Default for GameplayState {
-    fn default() -> Self {
-        Self::Playing
-    }
-}
-
 impl Display for GameplayState {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
         fmt.write_str(match self {
@@ -35,6 +45,8 @@ impl Display for GameplayState {
 pub struct Gameplay {
     pub state: GameplayState,
     pub moves_count: u32,
+    pub pushes_count: u32,
+    pub hints_count: u32,
 }
 
 #[derive(Default)]
@@ -47,10 +59,338 @@ pub struct EventQueue {
     pub events: Vec<Event>,
 }
 
+/// Sounds the systems asked for, played by the binary when it has audio
+#[derive(Default)]
+pub struct SoundQueue {
+    pub sounds: Vec<&'static str>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PushRule {
+    // A single box at a time, as in standard Sokoban
+    #[default]
+    Classic,
+    // Every box in a row moves together
+    Chain,
+}
+
+impl FromStr for PushRule {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_lowercase().as_str() {
+            "classic" => Ok(PushRule::Classic),
+            "chain" => Ok(PushRule::Chain),
+            value => Err(format!("Unknown push rule {}", value)),
+        }
+    }
+}
+
+/// Recorded moves fed into the InputQueue as if they were played
+pub struct Replay {
+    pub steps: Vec<Direction>,
+    pub cursor: usize,
+    // steps per second
+    pub speed: f32,
+    pub paused: bool,
+    pub fast_forward: bool,
+    // while paused, play a single step
+    pub step_requested: bool,
+    pub next_step_at: Duration,
+}
+
+impl Default for Replay {
+    fn default() -> Self {
+        Self {
+            steps: Vec::new(),
+            cursor: 0,
+            speed: REPLAY_SPEED,
+            paused: false,
+            fast_forward: false,
+            step_requested: false,
+            next_step_at: Duration::default(),
+        }
+    }
+}
+
+impl Replay {
+    pub fn is_active(&self) -> bool {
+        !self.steps.is_empty()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.cursor >= self.steps.len()
+    }
+
+    pub fn rewind(&mut self) {
+        self.cursor = 0;
+        self.step_requested = false;
+        self.next_step_at = Duration::default();
+    }
+
+    pub fn step_interval(&self) -> Duration {
+        let speed = if self.fast_forward {
+            self.speed * REPLAY_FAST_FORWARD
+        } else {
+            self.speed
+        };
+
+        Duration::from_secs_f32(1.0 / speed)
+    }
+}
+
+/// Boxes that can no longer reach a spot, kept for the RenderingSystem
+#[derive(Default)]
+pub struct Deadlocks {
+    pub boxes: HashSet<Index>,
+}
+
+/// Debugging aids drawn over the level, they stay on between levels
+#[derive(Default)]
+pub struct DebugOverlay {
+    pub show_dead_squares: bool,
+}
+
+/// Box cell and the direction to push it
+pub type HintPush = (Cell, Direction);
+
+/// The next push suggested by the solver, cleared by the next move
+#[derive(Default)]
+pub struct Hint {
+    pub push: Option<HintPush>,
+    // why there is no push to suggest
+    pub error: Option<String>,
+}
+
+/// Size of the loaded level in tiles
+#[derive(Default)]
+pub struct MapSize {
+    pub width: u8,
+    pub height: u8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CameraMode {
+    // shows the whole level, also after the window is resized
+    Fit,
+    Free,
+    Follow,
+}
+
+/// The part of the level that is shown, `center` is in level pixels before zooming
+pub struct Camera {
+    pub mode: CameraMode,
+    pub center: (f32, f32),
+    pub zoom: f32,
+    pub scaling: Scaling,
+    // window size in pixels
+    pub viewport: (f32, f32),
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self {
+            mode: CameraMode::Fit,
+            center: (0.0, 0.0),
+            zoom: 1.0,
+            scaling: Scaling::default(),
+            viewport: (WINDOW_WIDTH, WINDOW_HEIGHT),
+        }
+    }
+}
+
+impl Camera {
+    /// Shows the whole level beside the HUD, as large as it fits
+    pub fn fit(&mut self, map_size: &MapSize) {
+        let width = map_size.width as f32 * TILE_WIDTH;
+        let height = map_size.height as f32 * TILE_WIDTH;
+        let (area_width, area_height) = self.area();
+
+        self.mode = CameraMode::Fit;
+        self.center = (width / 2.0, height / 2.0);
+        let zoom = f32::min(area_width / width, area_height / height);
+        self.set_zoom(match self.scaling {
+            Scaling::Integer if zoom >= 1.0 => zoom.floor(),
+            _ => zoom,
+        });
+    }
+
+    /// The window changed size, a fitted level is fitted again
+    pub fn resize(&mut self, viewport: (f32, f32), map_size: &MapSize) {
+        self.viewport = viewport;
+        if self.mode == CameraMode::Fit {
+            self.fit(map_size);
+        }
+    }
+
+    /// Moves by two tiles and stops following the player
+    pub fn pan(&mut self, direction: Direction) {
+        let step = 2.0 * TILE_WIDTH;
+        let (x, y) = self.center;
+        self.center = match direction {
+            Direction::Up => (x, y - step),
+            Direction::Down => (x, y + step),
+            Direction::Left => (x - step, y),
+            Direction::Right => (x + step, y),
+        };
+        self.mode = CameraMode::Free;
+    }
+
+    /// Zooming by hand stops fitting, following the player goes on
+    pub fn zoom_in(&mut self) {
+        let zoom = match self.scaling {
+            Scaling::Integer if self.zoom >= 1.0 => self.zoom.floor() + 1.0,
+            // don't skip over the original size
+            Scaling::Integer => f32::min(self.zoom * ZOOM_STEP, 1.0),
+            Scaling::Filtered => self.zoom * ZOOM_STEP,
+        };
+        self.zoom_by_hand(zoom);
+    }
+
+    pub fn zoom_out(&mut self) {
+        let zoom = match self.scaling {
+            Scaling::Integer if self.zoom > 1.0 => self.zoom.ceil() - 1.0,
+            _ => self.zoom / ZOOM_STEP,
+        };
+        self.zoom_by_hand(zoom);
+    }
+
+    fn zoom_by_hand(&mut self, zoom: f32) {
+        if self.mode == CameraMode::Fit {
+            self.mode = CameraMode::Free;
+        }
+        self.set_zoom(zoom);
+    }
+
+    fn set_zoom(&mut self, zoom: f32) {
+        self.zoom = zoom.clamp(MIN_ZOOM, MAX_ZOOM);
+    }
+
+    /// Where the top left corner of the level is drawn in the window
+    pub fn offset(&self) -> (f32, f32) {
+        let (area_width, area_height) = self.area();
+        let offset = (
+            area_width / 2.0 - self.center.0 * self.zoom,
+            area_height / 2.0 - self.center.1 * self.zoom,
+        );
+
+        // Whole pixels keep the sprites sharp
+        match self.scaling {
+            Scaling::Integer => (offset.0.round(), offset.1.round()),
+            Scaling::Filtered => offset,
+        }
+    }
+
+    // The window without the HUD column
+    fn area(&self) -> (f32, f32) {
+        (
+            f32::max(self.viewport.0 - HUD_WIDTH, TILE_WIDTH),
+            self.viewport.1,
+        )
+    }
+}
+
+#[derive(Default)]
+pub struct LevelPack {
+    // Used to name exported solutions
+    pub name: String,
+    pub levels: Vec<String>,
+    pub current: usize,
+    // Summed over the levels won so far
+    pub total_moves: u32,
+    pub total_pushes: u32,
+    // Why the current level was rejected, shown instead of the board
+    pub load_error: Option<String>,
+}
+
+impl LevelPack {
+    pub fn current_map(&self) -> Option<&str> {
+        self.levels.get(self.current).map(|x| x.as_str())
+    }
+
+    /// True once the last level of the pack has been won
+    pub fn is_complete(&self) -> bool {
+        self.current >= self.levels.len()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Move {
+    pub direction: Direction,
+    // player and every pushed entity, in push order
+    pub ids: Vec<Index>,
+    pub is_push: bool,
+}
+
+impl Move {
+    fn reversed(&self) -> Self {
+        Self {
+            direction: self.direction.opposite(),
+            ids: self.ids.clone(),
+            is_push: self.is_push,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct MoveHistory {
+    undo_stack: Vec<Move>,
+    redo_stack: Vec<Move>,
+}
+
+impl MoveHistory {
+    pub fn record(&mut self, step: Move) {
+        self.undo_stack.push(step);
+        self.redo_stack.clear();
+    }
+
+    /// Returns the move that takes back the last recorded one
+    pub fn undo(&mut self) -> Option<Move> {
+        let step = self.undo_stack.pop()?;
+        let reversed = step.reversed();
+        self.redo_stack.push(step);
+
+        Some(reversed)
+    }
+
+    pub fn last(&self) -> Option<&Move> {
+        self.undo_stack.last()
+    }
+
+    /// The moves played so far in LURD notation, undone moves are left out
+    pub fn lurd(&self) -> String {
+        self.undo_stack
+            .iter()
+            .map(|step| lurd_char(step.direction, step.is_push))
+            .collect()
+    }
+
+    pub fn redo(&mut self) -> Option<Move> {
+        let step = self.redo_stack.pop()?;
+        self.undo_stack.push(step.clone());
+
+        Some(step)
+    }
+}
+
 pub fn register_resources(world: &mut World) {
     world.insert(InputQueue::default());
     world.insert(Gameplay::default());
     world.insert(Time::default());
     world.insert(EventQueue::default());
-    world.insert(AudioStore::default());
+    world.insert(SoundQueue::default());
+    world.insert(Theme::default());
+    world.insert(MoveHistory::default());
+    world.insert(LevelPack::default());
+    world.insert(MapSize::default());
+    world.insert(PushRule::default());
+    world.insert(Replay::default());
+    world.insert(Hint::default());
+    world.insert(HintSearch::default());
+    world.insert(Deadlocks::default());
+    world.insert(DeadSquares::default());
+    world.insert(DebugOverlay::default());
+    world.insert(Camera::default());
+    world.insert(Settings::default());
 }
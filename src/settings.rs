@@ -0,0 +1,89 @@
+use crate::constants::DEFAULT_THEME;
+use crate::resources::PushRule;
+use serde::Deserialize;
+use std::fmt;
+use std::fmt::Display;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// How the level is scaled to the window
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Scaling {
+    // whole multiples of the tile size with sharp pixels, smaller only for big levels
+    #[default]
+    Integer,
+    // any size, smoothed
+    Filtered,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub push_rule: PushRule,
+    // how long a move slides between tiles, 0 turns the animation off
+    pub move_duration_ms: u64,
+    pub scaling: Scaling,
+    pub fullscreen: bool,
+    // a manifest in the themes directory, without its extension
+    pub theme: String,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            push_rule: PushRule::default(),
+            move_duration_ms: 120,
+            scaling: Scaling::default(),
+            fullscreen: false,
+            theme: DEFAULT_THEME.to_string(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum SettingsError {
+    Io {
+        path: PathBuf,
+        error: io::Error,
+    },
+    Parse {
+        path: PathBuf,
+        error: toml::de::Error,
+    },
+}
+
+impl Display for SettingsError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SettingsError::Io { path, error } => {
+                write!(fmt, "Could not read settings {}: {}", path.display(), error)
+            }
+            SettingsError::Parse { path, error } => {
+                write!(fmt, "Invalid settings {}: {}", path.display(), error)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SettingsError {}
+
+/// Reads the settings file, a missing file means the default settings
+pub fn load_settings(path: &Path) -> Result<Settings, SettingsError> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Settings::default()),
+        Err(error) => {
+            return Err(SettingsError::Io {
+                path: path.to_path_buf(),
+                error,
+            })
+        }
+    };
+
+    toml::from_str(&content).map_err(|error| SettingsError::Parse {
+        path: path.to_path_buf(),
+        error,
+    })
+}
@@ -0,0 +1,172 @@
+use crate::actions::{Action, Direction};
+use crate::resources::{EventQueue, Gameplay, GameplayState, InputQueue, MoveHistory};
+use crate::systems::{GameplayStateSystem, InputSystem};
+use specs::{RunNow, World, WorldExt};
+use std::fmt;
+use std::fmt::Display;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+#[derive(Debug)]
+pub enum SolutionError {
+    // steps are counted from 1, as in the LURD string
+    InvalidCharacter { step: usize, char: char },
+    Blocked { step: usize },
+    PushMismatch { step: usize },
+    Unsolved,
+}
+
+impl Display for SolutionError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SolutionError::InvalidCharacter { step, char } => {
+                write!(fmt, "Step {} is {:?}, expected one of lurdLURD", step, char)
+            }
+            SolutionError::Blocked { step } => write!(fmt, "Step {} is blocked", step),
+            SolutionError::PushMismatch { step } => write!(
+                fmt,
+                "Step {} does not match the level, pushes must be uppercase",
+                step
+            ),
+            SolutionError::Unsolved => fmt.write_str("The moves do not solve the level"),
+        }
+    }
+}
+
+impl std::error::Error for SolutionError {}
+
+/// LURD notation, lowercase for moves and uppercase for pushes
+pub fn lurd_char(direction: Direction, is_push: bool) -> char {
+    let char = match direction {
+        Direction::Left => 'l',
+        Direction::Up => 'u',
+        Direction::Right => 'r',
+        Direction::Down => 'd',
+    };
+
+    if is_push {
+        char.to_ascii_uppercase()
+    } else {
+        char
+    }
+}
+
+/// Turns a LURD string into directions and whether each one is a push,
+/// whitespace is ignored so long solutions can be wrapped
+pub fn parse_lurd(lurd: &str) -> Result<Vec<(Direction, bool)>, SolutionError> {
+    lurd.chars()
+        .filter(|char| !char.is_whitespace())
+        .enumerate()
+        .map(|(index, char)| {
+            let direction = match char.to_ascii_lowercase() {
+                'l' => Direction::Left,
+                'u' => Direction::Up,
+                'r' => Direction::Right,
+                'd' => Direction::Down,
+                _ => {
+                    return Err(SolutionError::InvalidCharacter {
+                        step: index + 1,
+                        char,
+                    })
+                }
+            };
+
+            Ok((direction, char.is_ascii_uppercase()))
+        })
+        .collect()
+}
+
+/// Plays the solution on the loaded level without a window and checks that it wins
+pub fn verify_solution(world: &World, lurd: &str) -> Result<(), SolutionError> {
+    for (index, (direction, is_push)) in parse_lurd(lurd)?.into_iter().enumerate() {
+        let step = index + 1;
+        let moves_count = world.read_resource::<Gameplay>().moves_count;
+
+        world
+            .write_resource::<InputQueue>()
+            .actions
+            .push_back(Action::Move(direction));
+        InputSystem {}.run_now(world);
+        // Sounds are not needed here
+        world.write_resource::<EventQueue>().events.clear();
+
+        if world.read_resource::<Gameplay>().moves_count == moves_count {
+            return Err(SolutionError::Blocked { step });
+        }
+
+        let move_history = world.read_resource::<MoveHistory>();
+        let last_move_is_push = matches!(move_history.last(), Some(last_move) if last_move.is_push);
+        if last_move_is_push != is_push {
+            return Err(SolutionError::PushMismatch { step });
+        }
+    }
+
+    GameplayStateSystem {}.run_now(world);
+    match world.read_resource::<Gameplay>().state {
+        GameplayState::Won => Ok(()),
+        GameplayState::Playing => Err(SolutionError::Unsolved),
+    }
+}
+
+pub fn read_solution(path: &Path) -> io::Result<String> {
+    fs::read_to_string(path)
+}
+
+pub fn write_solution(path: &Path, lurd: &str) -> io::Result<()> {
+    if let Some(directory) = path.parent() {
+        fs::create_dir_all(directory)?;
+    }
+
+    fs::write(path, format!("{}\n", lurd))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{load, play, DOWN, RIGHT, UP};
+
+    // Two pushes to the right solve it
+    const LEVEL: &str = "######\n#@$ .#\n#    #\n######";
+
+    #[test]
+    fn played_moves_verify_as_a_solution() {
+        let mut world = load(LEVEL);
+        play(&mut world, &[DOWN, UP, RIGHT]);
+        play(&mut world, &[Action::Undo, RIGHT, RIGHT]);
+
+        let lurd = world.read_resource::<MoveHistory>().lurd();
+        assert_eq!(lurd, "duRR");
+        assert!(verify_solution(&load(LEVEL), &lurd).is_ok());
+    }
+
+    #[test]
+    fn whitespace_in_solutions_is_ignored() {
+        let steps = parse_lurd("dU\n r ").unwrap();
+
+        assert_eq!(
+            steps,
+            vec![
+                (Direction::Down, false),
+                (Direction::Up, true),
+                (Direction::Right, false)
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_wrong_solutions() {
+        let verify = |lurd| verify_solution(&load(LEVEL), lurd).unwrap_err();
+
+        assert!(matches!(
+            verify("Rx"),
+            SolutionError::InvalidCharacter { step: 2, char: 'x' }
+        ));
+        assert!(matches!(verify("u"), SolutionError::Blocked { step: 1 }));
+        assert!(matches!(
+            verify("rR"),
+            SolutionError::PushMismatch { step: 1 }
+        ));
+        assert!(matches!(verify("R"), SolutionError::Unsolved));
+    }
+}
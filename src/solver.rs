@@ -0,0 +1,369 @@
+use crate::actions::Direction;
+use crate::board::{Board, Cell, DIRECTIONS};
+use crate::components::BoxColor;
+use crate::constants::{SOLVER_MAX_NODES, SOLVER_MAX_TIME};
+use crate::deadlock::DeadSquares;
+use crate::solution::lurd_char;
+use specs::World;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::fmt::Display;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+pub struct SolverLimits {
+    // expanded push states
+    pub max_nodes: usize,
+    pub max_time: Duration,
+}
+
+impl Default for SolverLimits {
+    fn default() -> Self {
+        Self {
+            max_nodes: SOLVER_MAX_NODES,
+            max_time: SOLVER_MAX_TIME,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum SolveError {
+    NoLevel,
+    Unsolvable,
+    NodeLimit { nodes: usize },
+    TimeLimit { time: Duration },
+    Cancelled,
+}
+
+impl Display for SolveError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SolveError::NoLevel => fmt.write_str("There is no level to solve"),
+            SolveError::Unsolvable => fmt.write_str("The level has no solution"),
+            SolveError::NodeLimit { nodes } => {
+                write!(fmt, "No solution found within {} positions", nodes)
+            }
+            SolveError::TimeLimit { time } => {
+                write!(fmt, "No solution found within {:.1}s", time.as_secs_f32())
+            }
+            SolveError::Cancelled => fmt.write_str("The search was cancelled"),
+        }
+    }
+}
+
+impl std::error::Error for SolveError {}
+
+// Sorted by cell, so that the same layout always compares equal
+type Boxes = Vec<(Cell, BoxColor)>;
+
+// Boxes plus the top-left cell the player can walk to, positions the player
+// can walk between without pushing are the same search state
+type StateKey = (Boxes, Cell);
+
+struct Node {
+    boxes: Boxes,
+    // where the player stands right after the push
+    player: Cell,
+    parent: Option<usize>,
+    // box cell before the push and the push direction
+    push: Option<(Cell, Direction)>,
+    pushes: u32,
+}
+
+/// Solves the level as it currently is in the world
+pub fn solve_world(world: &World, limits: &SolverLimits) -> Result<String, SolveError> {
+    let board = Board::from_world(world).ok_or(SolveError::NoLevel)?;
+    solve(&board, limits, &AtomicBool::new(false))
+}
+
+/// A* over push states, the solution has the fewest pushes possible.
+/// Returns it in LURD notation, or gives up once `cancel` is set.
+pub fn solve(
+    board: &Board,
+    limits: &SolverLimits,
+    cancel: &AtomicBool,
+) -> Result<String, SolveError> {
+    let started_at = Instant::now();
+    let dead_squares = DeadSquares::new(board);
+
+    let mut boxes: Boxes = board
+        .boxes
+        .iter()
+        .map(|(cell, color)| (*cell, *color))
+        .collect();
+    boxes.sort_unstable_by_key(|(cell, _color)| *cell);
+
+    let mut nodes = vec![Node {
+        boxes,
+        player: board.player,
+        parent: None,
+        push: None,
+        pushes: 0,
+    }];
+    let mut best_pushes: HashMap<StateKey, u32> = HashMap::new();
+    best_pushes.insert(state_key(board, &nodes[0]), 0);
+
+    let mut open = BinaryHeap::new();
+    open.push(Reverse((estimate(board, &nodes[0].boxes), 0)));
+    let mut expanded = 0;
+
+    while let Some(Reverse((_estimate, index))) = open.pop() {
+        if is_solved(board, &nodes[index].boxes) {
+            return Ok(solution(board, &nodes, index));
+        }
+
+        let key = state_key(board, &nodes[index]);
+        if matches!(best_pushes.get(&key), Some(pushes) if *pushes < nodes[index].pushes) {
+            continue;
+        }
+
+        if cancel.load(Ordering::Relaxed) {
+            return Err(SolveError::Cancelled);
+        }
+
+        expanded += 1;
+        if expanded > limits.max_nodes {
+            return Err(SolveError::NodeLimit {
+                nodes: limits.max_nodes,
+            });
+        }
+        if expanded % 1000 == 0 && started_at.elapsed() > limits.max_time {
+            return Err(SolveError::TimeLimit {
+                time: limits.max_time,
+            });
+        }
+
+        for child in expand(board, &dead_squares, &nodes[index], index) {
+            let key = state_key(board, &child);
+            if matches!(best_pushes.get(&key), Some(pushes) if *pushes <= child.pushes) {
+                continue;
+            }
+
+            best_pushes.insert(key, child.pushes);
+            open.push(Reverse((
+                child.pushes + estimate(board, &child.boxes),
+                nodes.len(),
+            )));
+            nodes.push(child);
+        }
+    }
+
+    Err(SolveError::Unsolvable)
+}
+
+fn expand(board: &Board, dead_squares: &DeadSquares, node: &Node, index: usize) -> Vec<Node> {
+    let box_cells: HashSet<Cell> = node.boxes.iter().map(|(cell, _color)| *cell).collect();
+    let reachable = reachable_cells(board, &box_cells, node.player);
+    let mut children = Vec::new();
+
+    for (box_index, (cell, color)) in node.boxes.iter().enumerate() {
+        for direction in DIRECTIONS.iter() {
+            match board.neighbor(*cell, direction.opposite()) {
+                Some(behind) if reachable.contains(&behind) => (),
+                _ => continue,
+            }
+
+            let target = match board.neighbor(*cell, *direction) {
+                Some(target) if !board.walls.contains(&target) && !box_cells.contains(&target) => {
+                    target
+                }
+                _ => continue,
+            };
+            if dead_squares.is_dead(target, *color) {
+                continue;
+            }
+
+            let mut boxes = node.boxes.clone();
+            boxes[box_index] = (target, *color);
+            boxes.sort_unstable_by_key(|(cell, _color)| *cell);
+
+            children.push(Node {
+                boxes,
+                player: *cell,
+                parent: Some(index),
+                push: Some((*cell, *direction)),
+                pushes: node.pushes + 1,
+            });
+        }
+    }
+
+    children
+}
+
+fn is_solved(board: &Board, boxes: &[(Cell, BoxColor)]) -> bool {
+    boxes
+        .iter()
+        .all(|(cell, color)| board.spots.get(cell) == Some(color))
+}
+
+// Every push moves a box by one tile, so this never overestimates
+fn estimate(board: &Board, boxes: &[(Cell, BoxColor)]) -> u32 {
+    boxes
+        .iter()
+        .map(|(cell, color)| {
+            board
+                .spots
+                .iter()
+                .filter(|(_spot, spot_color)| *spot_color == color)
+                .map(|(spot, _color)| distance(*cell, *spot))
+                .min()
+                .unwrap_or(0)
+        })
+        .sum()
+}
+
+fn distance(from: Cell, to: Cell) -> u32 {
+    let x = (from.0 as i32 - to.0 as i32).abs();
+    let y = (from.1 as i32 - to.1 as i32).abs();
+
+    (x + y) as u32
+}
+
+fn state_key(board: &Board, node: &Node) -> StateKey {
+    let box_cells: HashSet<Cell> = node.boxes.iter().map(|(cell, _color)| *cell).collect();
+    let player = reachable_cells(board, &box_cells, node.player)
+        .into_iter()
+        .min()
+        .unwrap_or(node.player);
+
+    (node.boxes.clone(), player)
+}
+
+pub(crate) fn reachable_cells(board: &Board, boxes: &HashSet<Cell>, from: Cell) -> HashSet<Cell> {
+    let mut reachable = HashSet::new();
+    let mut stack = vec![from];
+
+    while let Some(cell) = stack.pop() {
+        if !reachable.insert(cell) {
+            continue;
+        }
+
+        for direction in DIRECTIONS.iter() {
+            if let Some(next) = board.neighbor(cell, *direction) {
+                if !board.walls.contains(&next) && !boxes.contains(&next) {
+                    stack.push(next);
+                }
+            }
+        }
+    }
+
+    reachable
+}
+
+/// Shortest walk that doesn't push anything
+fn find_path(board: &Board, boxes: &HashSet<Cell>, from: Cell, to: Cell) -> Option<Vec<Direction>> {
+    let mut previous: HashMap<Cell, (Cell, Direction)> = HashMap::new();
+    let mut queue = VecDeque::new();
+    queue.push_back(from);
+
+    while let Some(cell) = queue.pop_front() {
+        if cell == to {
+            let mut path = Vec::new();
+            let mut cell = to;
+            while cell != from {
+                let (previous_cell, direction) = previous[&cell];
+                path.push(direction);
+                cell = previous_cell;
+            }
+            path.reverse();
+            return Some(path);
+        }
+
+        for direction in DIRECTIONS.iter() {
+            if let Some(next) = board.neighbor(cell, *direction) {
+                let is_free = !board.walls.contains(&next) && !boxes.contains(&next);
+                if is_free && next != from && !previous.contains_key(&next) {
+                    previous.insert(next, (cell, *direction));
+                    queue.push_back(next);
+                }
+            }
+        }
+    }
+
+    None
+}
+
+// Replays the pushes from the start, adding the walks between them
+fn solution(board: &Board, nodes: &[Node], index: usize) -> String {
+    let mut pushes = Vec::new();
+    let mut current = Some(index);
+    while let Some(index) = current {
+        if let Some(push) = nodes[index].push {
+            pushes.push(push);
+        }
+        current = nodes[index].parent;
+    }
+    pushes.reverse();
+
+    let mut boxes: HashSet<Cell> = board.boxes.keys().cloned().collect();
+    let mut player = board.player;
+    let mut lurd = String::new();
+
+    for (cell, direction) in pushes {
+        let behind = board
+            .neighbor(cell, direction.opposite())
+            .expect("Solver push without a player cell");
+        let path = find_path(board, &boxes, player, behind).expect("Solver push out of reach");
+        lurd.extend(path.into_iter().map(|step| lurd_char(step, false)));
+        lurd.push(lurd_char(direction, true));
+
+        let target = board
+            .neighbor(cell, direction)
+            .expect("Solver push off the map");
+        boxes.remove(&cell);
+        boxes.insert(target);
+        player = cell;
+    }
+
+    lurd
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solution::verify_solution;
+    use crate::testing::load;
+
+    #[test]
+    fn solution_verifies() {
+        let level = "#######\n#     #\n# $@$ #\n#.   .#\n#######";
+        let lurd = solve_world(&load(level), &SolverLimits::default()).unwrap();
+
+        assert!(verify_solution(&load(level), &lurd).is_ok());
+    }
+
+    #[test]
+    fn box_in_a_corner_is_unsolvable() {
+        let world = load("#####\n#$  #\n# @.#\n#####");
+
+        assert!(matches!(
+            solve_world(&world, &SolverLimits::default()),
+            Err(SolveError::Unsolvable)
+        ));
+    }
+
+    #[test]
+    fn stops_at_the_node_limit() {
+        let world = load("######\n#@$ .#\n######");
+        let limits = SolverLimits {
+            max_nodes: 1,
+            ..SolverLimits::default()
+        };
+
+        assert!(matches!(
+            solve_world(&world, &limits),
+            Err(SolveError::NodeLimit { nodes: 1 })
+        ));
+    }
+
+    #[test]
+    fn stops_when_cancelled() {
+        let board = Board::from_world(&load("######\n#@$ .#\n######")).unwrap();
+
+        assert!(matches!(
+            solve(&board, &SolverLimits::default(), &AtomicBool::new(true)),
+            Err(SolveError::Cancelled)
+        ));
+    }
+}
@@ -1,9 +1,21 @@
+mod animation_system;
+mod camera_system;
 mod event_system;
 mod gameplay_state_system;
+mod hint_system;
 mod input_system;
+mod player_sprite_system;
+#[cfg(feature = "ggez")]
 mod rendering_system;
+mod replay_system;
 
+pub use self::animation_system::AnimationSystem;
+pub use self::camera_system::CameraSystem;
 pub use self::event_system::EventSystem;
 pub use self::gameplay_state_system::GameplayStateSystem;
+pub use self::hint_system::HintSystem;
 pub use self::input_system::InputSystem;
+pub use self::player_sprite_system::PlayerSpriteSystem;
+#[cfg(feature = "ggez")]
 pub use self::rendering_system::RenderingSystem;
+pub use self::replay_system::ReplaySystem;
@@ -0,0 +1,68 @@
+use crate::components::*;
+use crate::resources::Time;
+use specs::{Join, Read, System, WriteStorage};
+
+pub struct AnimationSystem {}
+
+impl<'a> System<'a> for AnimationSystem {
+    type SystemData = (
+        Read<'a, Time>,
+        WriteStorage<'a, Animation>,
+        WriteStorage<'a, Renderable>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (time, mut animations, mut renderables) = data;
+
+        for (animation, renderable) in (&mut animations, &mut renderables).join() {
+            let frames = animation.elapsed_frames(time.delta);
+            let frame_count = renderable.frame_count();
+
+            match animation.mode.clone() {
+                AnimationMode::Loop => animation.frame = frames % frame_count,
+                AnimationMode::Once { .. } if frames < frame_count => animation.frame = frames,
+                AnimationMode::Once { then: Some(then) } => {
+                    renderable.set_state(&then);
+                    animation.restart(time.delta, AnimationMode::Loop);
+                }
+                AnimationMode::Once { then: None } => animation.frame = frame_count - 1,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::components::{Animation, Box, Renderable};
+    use crate::resources::Time;
+    use crate::testing::{load, RIGHT};
+    use crate::{perform_action, run_gameplay};
+    use specs::{Join, World, WorldExt};
+    use std::time::Duration;
+
+    fn box_image(world: &World) -> String {
+        let boxes = world.read_storage::<Box>();
+        let renderables = world.read_storage::<Renderable>();
+        let animations = world.read_storage::<Animation>();
+        let (_box, renderable, animation) =
+            (&boxes, &renderables, &animations).join().next().unwrap();
+        renderable.path(animation.frame)
+    }
+
+    #[test]
+    fn boxes_settle_once_on_their_spot() {
+        let mut world = load("#####\n#@$.#\n#####");
+        perform_action(&mut world, RIGHT);
+
+        let mut images = Vec::new();
+        for _ in 0..30 {
+            run_gameplay(&world);
+            images.push(box_image(&world));
+            world.write_resource::<Time>().delta += Duration::from_millis(100);
+        }
+
+        assert!(images.iter().any(|image| image.contains("settle_1")));
+        assert!(images.iter().any(|image| image.contains("settle_2")));
+        assert!(!images.last().unwrap().contains("settle"));
+    }
+}
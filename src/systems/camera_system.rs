@@ -0,0 +1,74 @@
+use crate::components::*;
+use crate::constants::TILE_WIDTH;
+use crate::resources::{Camera, CameraMode, Time};
+use specs::{Join, Read, ReadStorage, System, Write};
+
+/// Keeps the player in the middle of the screen in follow mode
+pub struct CameraSystem {}
+
+impl<'a> System<'a> for CameraSystem {
+    type SystemData = (
+        Write<'a, Camera>,
+        Read<'a, Time>,
+        ReadStorage<'a, Player>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, Tween>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (mut camera, time, players, positions, tweens) = data;
+
+        if camera.mode != CameraMode::Follow {
+            return;
+        }
+
+        for (_player, position, tween) in (&players, &positions, tweens.maybe()).join() {
+            let (x, y) = match tween {
+                Some(tween) => tween.position(position, time.delta),
+                None => (position.x as f32, position.y as f32),
+            };
+            camera.center = ((x + 0.5) * TILE_WIDTH, (y + 0.5) * TILE_WIDTH);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::actions::{Action, Direction};
+    use crate::perform_action;
+    use crate::resources::{Camera, CameraMode, MapSize};
+    use crate::settings::Scaling;
+    use crate::testing::{load, play, RIGHT};
+    use specs::WorldExt;
+
+    #[test]
+    fn follows_the_player_until_panned() {
+        let mut world = load("######\n#@   #\n######");
+        perform_action(&mut world, Action::ToggleCameraFollow);
+        play(&mut world, &[RIGHT]);
+        assert_eq!(world.read_resource::<Camera>().center, (80.0, 48.0));
+
+        play(&mut world, &[Action::PanCamera(Direction::Down), RIGHT]);
+        let camera = world.read_resource::<Camera>();
+        assert_eq!(camera.mode, CameraMode::Free);
+        assert_eq!(camera.center, (80.0, 112.0));
+    }
+
+    #[test]
+    fn fitting_keeps_whole_zoom_steps_unless_filtered() {
+        let map_size = MapSize {
+            width: 5,
+            height: 3,
+        };
+        let mut camera = Camera::default();
+        camera.resize((800.0, 600.0), &map_size);
+        assert_eq!(camera.zoom, 3.0);
+
+        camera.resize((595.0, 600.0), &map_size);
+        assert_eq!(camera.zoom, 2.0);
+
+        camera.scaling = Scaling::Filtered;
+        camera.resize((800.0, 600.0), &map_size);
+        assert_eq!(camera.zoom, 525.0 / 160.0);
+    }
+}
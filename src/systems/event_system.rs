@@ -1,44 +1,106 @@
 use crate::{
-    audio::AudioStore,
+    board::Board,
     components::*,
-    events::{BoxPlacedOnSpot, EntityMoved, Event},
-    resources::EventQueue,
+    deadlock::{find_deadlocks, DeadSquares},
+    events::{BoxPlacedOnSpot, Deadlock, EntityMoved, Event},
+    resources::{Deadlocks, EventQueue, MapSize, SoundQueue, Time},
+    settings::Settings,
 };
-use specs::{Entities, Join, ReadStorage, System, Write};
-use std::collections::HashMap;
+use specs::{Entities, Join, Read, ReadStorage, System, Write, WriteStorage};
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
 
 pub struct EventSystem {}
 
 impl<'a> System<'a> for EventSystem {
     type SystemData = (
         Write<'a, EventQueue>,
-        Write<'a, AudioStore>,
+        Write<'a, SoundQueue>,
+        Write<'a, Deadlocks>,
+        Read<'a, MapSize>,
+        Read<'a, DeadSquares>,
+        Read<'a, Settings>,
+        Read<'a, Time>,
         Entities<'a>,
         ReadStorage<'a, Box>,
         ReadStorage<'a, BoxSpot>,
+        ReadStorage<'a, Player>,
+        ReadStorage<'a, Wall>,
         ReadStorage<'a, Position>,
+        WriteStorage<'a, Tween>,
+        WriteStorage<'a, Renderable>,
+        WriteStorage<'a, Animation>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
         let mut new_events = Vec::new();
+        let mut is_box_moved = false;
 
-        let (mut event_queue, mut audio_store, entities, boxes, box_spots, positions) = data;
+        let (
+            mut event_queue,
+            mut sound_queue,
+            mut deadlocks,
+            map_size,
+            dead_squares,
+            settings,
+            time,
+            entities,
+            boxes,
+            box_spots,
+            players,
+            walls,
+            positions,
+            mut tweens,
+            mut renderables,
+            mut animations,
+        ) = data;
 
         for event in event_queue.events.drain(..) {
-            println!("New Event: {:?}", event);
-
             match event {
-                Event::BoxPlacedOnSpot(BoxPlacedOnSpot { is_correct_spot }) => {
+                Event::BoxPlacedOnSpot(BoxPlacedOnSpot {
+                    id,
+                    is_correct_spot,
+                }) => {
+                    let entity = entities.entity(id);
+                    if let (true, Some(renderable), Some(animation)) = (
+                        is_correct_spot,
+                        renderables.get_mut(entity),
+                        animations.get_mut(entity),
+                    ) {
+                        // Settle once the box stopped sliding
+                        let started_at = tweens
+                            .get(entity)
+                            .map_or(time.delta, |tween| tween.started_at + tween.duration);
+                        renderable.set_state("settle");
+                        animation.restart(
+                            started_at,
+                            AnimationMode::Once {
+                                then: Some(DEFAULT_STATE.to_string()),
+                            },
+                        );
+                    }
+
                     let sound = if is_correct_spot {
                         "correct"
                     } else {
                         "incorrect"
                     };
-                    audio_store.play(sound);
+                    sound_queue.sounds.push(sound);
                 }
 
-                Event::EntityMoved(EntityMoved { id }) => {
+                Event::EntityMoved(EntityMoved { id, from }) => {
+                    let tween = Tween {
+                        from,
+                        started_at: time.delta,
+                        duration: Duration::from_millis(settings.move_duration_ms),
+                    };
+                    tweens
+                        .insert(entities.entity(id), tween)
+                        .expect("Tween error");
+
                     if let Some(the_box) = boxes.get(entities.entity(id)) {
+                        is_box_moved = true;
+
                         let box_spots_with_positions: HashMap<(u8, u8), &BoxSpot> =
                             (&positions, &box_spots)
                                 .join()
@@ -50,6 +112,7 @@ impl<'a> System<'a> for EventSystem {
                                 box_spots_with_positions.get(&(box_position.x, box_position.y))
                             {
                                 new_events.push(Event::BoxPlacedOnSpot(BoxPlacedOnSpot {
+                                    id,
                                     is_correct_spot: (box_spot.color == the_box.color),
                                 }));
                             }
@@ -58,9 +121,33 @@ impl<'a> System<'a> for EventSystem {
                 }
 
                 Event::PlayerHitObstacle => {
-                    audio_store.play("wall");
+                    sound_queue.sounds.push("wall");
                 }
+
+                Event::Deadlock(_) => {
+                    sound_queue.sounds.push("deadlock");
+                }
+            }
+        }
+
+        // Undo can free boxes again, so the whole board is checked every time
+        if is_box_moved {
+            let board =
+                Board::from_storages(&map_size, &positions, &players, &walls, &box_spots, &boxes);
+            let cells = board
+                .map(|board| find_deadlocks(&board, &dead_squares))
+                .unwrap_or_default();
+            let stuck: HashSet<_> = (&entities, &boxes, &positions)
+                .join()
+                .filter(|(_entity, _box, position)| cells.contains(&(position.x, position.y)))
+                .map(|(entity, _box, _position)| entity.id())
+                .collect();
+
+            let new_boxes: Vec<_> = stuck.difference(&deadlocks.boxes).copied().collect();
+            if !new_boxes.is_empty() {
+                new_events.push(Event::Deadlock(Deadlock { boxes: new_boxes }));
             }
+            deadlocks.boxes = stuck;
         }
 
         event_queue.events.append(&mut new_events);
@@ -23,12 +23,19 @@ impl<'a> System<'a> for GameplayStateSystem {
             .map(|t| ((t.0.x, t.0.y), t.1))
             .collect();
 
+        // An empty board, after the last level or a rejected one, has nothing to win
+        if boxes_by_position.is_empty() {
+            gameplay.state = GameplayState::Playing;
+            return;
+        }
+
         for (box_spot, position) in (&box_spots, &positions).join() {
             if let Some(the_box) = boxes_by_position.get(&(position.x, position.y)) {
                 if the_box.color == box_spot.color {
                     // We don't set GameplayState::Won because there could be multiple boxes
                     // We could keep track of box count / map
                 } else {
+                    gameplay.state = GameplayState::Playing;
                     return;
                 }
             } else {
@@ -40,3 +47,33 @@ impl<'a> System<'a> for GameplayStateSystem {
         gameplay.state = GameplayState::Won;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create_world;
+    use crate::testing::{load, play, DOWN, RIGHT};
+    use specs::WorldExt;
+
+    fn is_won(world: &specs::World) -> bool {
+        matches!(world.read_resource::<Gameplay>().state, GameplayState::Won)
+    }
+
+    #[test]
+    fn won_once_every_box_is_on_its_spot() {
+        let mut world = load("W W W W W\nW P RB RS W\nW W W W W");
+        play(&mut world, &[DOWN]);
+        assert!(!is_won(&world));
+
+        play(&mut world, &[RIGHT]);
+        assert!(is_won(&world));
+    }
+
+    #[test]
+    fn the_end_of_the_pack_is_not_won() {
+        let mut world = create_world();
+        play(&mut world, &[RIGHT]);
+
+        assert!(!is_won(&world));
+    }
+}
@@ -0,0 +1,18 @@
+use crate::hint::HintSearch;
+use crate::resources::Hint;
+use specs::{System, Write};
+
+/// Shows the hint once the search started by the Hint action is done
+pub struct HintSystem {}
+
+impl<'a> System<'a> for HintSystem {
+    type SystemData = (Write<'a, HintSearch>, Write<'a, Hint>);
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (mut hint_search, mut hint) = data;
+
+        if let Some(found) = hint_search.poll() {
+            *hint = found;
+        }
+    }
+}
@@ -1,10 +1,12 @@
+use crate::actions::{Action, Direction};
 use crate::components::*;
-use crate::constants::*;
 use crate::events::{EntityMoved, Event};
-use crate::resources::{EventQueue, Gameplay, InputQueue};
-use ggez::event;
+use crate::hint::HintSearch;
+use crate::resources::{
+    EventQueue, Gameplay, Hint, InputQueue, MapSize, Move, MoveHistory, PushRule, Time,
+};
 use specs::world::Index;
-use specs::{Entities, Join, ReadStorage, System, Write, WriteStorage};
+use specs::{Entities, Join, Read, ReadStorage, System, Write, WriteStorage};
 use std::collections::HashMap;
 
 pub struct InputSystem {}
@@ -14,11 +16,19 @@ impl<'a> System<'a> for InputSystem {
         Write<'a, EventQueue>,
         Write<'a, InputQueue>,
         Write<'a, Gameplay>,
+        Write<'a, MoveHistory>,
+        Write<'a, Hint>,
+        Write<'a, HintSearch>,
+        Read<'a, MapSize>,
+        Read<'a, PushRule>,
+        Read<'a, Time>,
         Entities<'a>,
         WriteStorage<'a, Position>,
-        ReadStorage<'a, Player>,
+        WriteStorage<'a, Player>,
+        ReadStorage<'a, Box>,
         ReadStorage<'a, Movable>,
         ReadStorage<'a, Immovable>,
+        ReadStorage<'a, Tween>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
@@ -26,17 +36,60 @@ impl<'a> System<'a> for InputSystem {
             mut event_queue,
             mut input_queue,
             mut gameplay,
+            mut move_history,
+            mut hint,
+            mut hint_search,
+            map_size,
+            push_rule,
+            time,
             entities,
             mut positions,
-            players,
+            mut players,
+            boxes,
             movables,
             immovables,
+            tweens,
         ) = data;
 
+        // Keep the input until the last move finished sliding
+        if (&tweens).join().any(|tween| !tween.is_finished(time.delta)) {
+            return;
+        }
+
         let mut to_move = Vec::new();
 
-        for (position, _player) in (&positions, &players).join() {
-            if let Some(key) = input_queue.keys_pressed.pop() {
+        for (position, player) in (&positions, &mut players).join() {
+            if let Some(action) = input_queue.actions.pop_front() {
+                // Undo / redo replay the recorded entities instead of searching again
+                let history_step = match action {
+                    Action::Undo => {
+                        let step = move_history.undo();
+                        if let Some(step) = &step {
+                            gameplay.moves_count -= 1;
+                            if step.is_push {
+                                gameplay.pushes_count -= 1;
+                            }
+                        }
+                        step
+                    }
+                    Action::Redo => {
+                        let step = move_history.redo();
+                        if let Some(step) = &step {
+                            gameplay.moves_count += 1;
+                            if step.is_push {
+                                gameplay.pushes_count += 1;
+                            }
+                        }
+                        step
+                    }
+                    _ => None,
+                };
+
+                if let Some(step) = history_step {
+                    to_move = step.ids.iter().map(|id| (step.direction, *id)).collect();
+                    continue;
+                }
+
                 // Get all positions
                 let mov: HashMap<(u8, u8), Index> = (&entities, &movables, &positions)
                     .join()
@@ -47,13 +100,18 @@ impl<'a> System<'a> for InputSystem {
                     .map(|t| ((t.2.x, t.2.y), t.0.id()))
                     .collect();
 
-                let (start, end, is_x) = match key {
-                    event::KeyCode::Up => (position.y, 0, false),
-                    event::KeyCode::Down => (position.y, MAP_HEIGHT, false),
-                    event::KeyCode::Left => (position.x, 0, true),
-                    event::KeyCode::Right => (position.x, MAP_WIDTH, true),
+                let direction = match action {
+                    Action::Move(direction) => direction,
                     _ => continue,
                 };
+                player.facing = direction;
+
+                let (start, end, is_x) = match direction {
+                    Direction::Up => (position.y, 0, false),
+                    Direction::Down => (position.y, map_size.height, false),
+                    Direction::Left => (position.x, 0, true),
+                    Direction::Right => (position.x, map_size.width, true),
+                };
 
                 // Creating range to limit search range
                 let range: Vec<u8> = if start < end {
@@ -62,8 +120,6 @@ impl<'a> System<'a> for InputSystem {
                     (end..=start).rev().collect()
                 };
 
-                println!("range {:?}", &range);
-
                 // iterate possible affecting entities in player movement direction
                 for x_or_y in range {
                     let pos = if is_x {
@@ -72,44 +128,184 @@ impl<'a> System<'a> for InputSystem {
                         (position.x, x_or_y)
                     };
 
-                    println!("pos to search: {:?}", &pos);
-
                     match mov.get(&pos) {
+                        // The player is always first, so a second box means a chain push
+                        Some(_id) if *push_rule == PushRule::Classic && to_move.len() == 2 => {
+                            to_move.clear();
+                            event_queue.events.push(Event::PlayerHitObstacle {});
+                            break;
+                        }
                         // id.clone() maybe better?
-                        Some(id) => to_move.push((key, *id)),
+                        Some(id) => to_move.push((direction, *id)),
                         None => match immov.get(&pos) {
                             Some(_id) => {
                                 to_move.clear();
                                 event_queue.events.push(Event::PlayerHitObstacle {});
+                                break;
                             }
                             // if there are no related immovable exit loop
                             None => break,
                         },
                     }
                 }
+
+                if !to_move.is_empty() {
+                    let is_push = to_move
+                        .iter()
+                        .any(|(_key, id)| boxes.get(entities.entity(*id)).is_some());
+
+                    gameplay.moves_count += 1;
+                    if is_push {
+                        gameplay.pushes_count += 1;
+                    }
+                    move_history.record(Move {
+                        direction,
+                        ids: to_move.iter().map(|(_key, id)| *id).collect(),
+                        is_push,
+                    });
+                }
             }
         }
 
+        // The hint was for the previous positions
         if !to_move.is_empty() {
-            gameplay.moves_count += 1;
-            println!("to_move: {:?}", to_move);
+            *hint = Hint::default();
+            hint_search.cancel();
         }
 
-        for (key, id) in to_move {
+        for (direction, id) in to_move {
             let position = positions.get_mut(entities.entity(id));
             if let Some(position) = position {
-                match key {
-                    event::KeyCode::Up => position.y -= 1,
-                    event::KeyCode::Down => position.y += 1,
-                    event::KeyCode::Left => position.x -= 1,
-                    event::KeyCode::Right => position.x += 1,
-                    _ => (),
+                let from = (position.x, position.y);
+                match direction {
+                    Direction::Up => position.y -= 1,
+                    Direction::Down => position.y += 1,
+                    Direction::Left => position.x -= 1,
+                    Direction::Right => position.x += 1,
                 }
+
+                event_queue
+                    .events
+                    .push(Event::EntityMoved(EntityMoved { id, from }))
             }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::actions::Action;
+    use crate::components::{Box, Player};
+    use crate::resources::{Gameplay, Time};
+    use crate::testing::{load, play, positions, DOWN, RIGHT};
+    use crate::{perform_action, run_gameplay};
+    use specs::{World, WorldExt};
+    use std::time::Duration;
+
+    const LEVEL: &str = "
+    W W W W W W
+    W P RB . RS W
+    W . . . . W
+    W W W W W W
+    ";
+    const TWO_BOXES: &str = "#######\n#@$$..#\n#######";
+
+    fn moves_count(world: &World) -> u32 {
+        world.read_resource::<Gameplay>().moves_count
+    }
 
-            event_queue
-                .events
-                .push(Event::EntityMoved(EntityMoved { id }))
+    fn pushes_count(world: &World) -> u32 {
+        world.read_resource::<Gameplay>().pushes_count
+    }
+
+    #[test]
+    fn undo_and_redo_replay_the_recorded_moves() {
+        let mut world = load(LEVEL);
+        play(&mut world, &[RIGHT, DOWN]);
+        assert_eq!(moves_count(&world), 2);
+
+        play(&mut world, &[Action::Undo, Action::Undo]);
+        assert_eq!(positions::<Player>(&world), vec![(1, 1)]);
+        assert_eq!(positions::<Box>(&world), vec![(2, 1)]);
+        assert_eq!(moves_count(&world), 0);
+
+        play(&mut world, &[Action::Redo]);
+        assert_eq!(positions::<Player>(&world), vec![(2, 1)]);
+        assert_eq!(positions::<Box>(&world), vec![(3, 1)]);
+        assert_eq!(moves_count(&world), 1);
+    }
+
+    #[test]
+    fn a_new_move_drops_the_redo_steps() {
+        let mut world = load(LEVEL);
+        play(&mut world, &[RIGHT, Action::Undo, DOWN, Action::Redo]);
+
+        assert_eq!(positions::<Player>(&world), vec![(1, 2)]);
+        assert_eq!(positions::<Box>(&world), vec![(2, 1)]);
+        assert_eq!(moves_count(&world), 1);
+    }
+
+    #[test]
+    fn nothing_to_undo_leaves_the_level_alone() {
+        let mut world = load(LEVEL);
+        play(&mut world, &[Action::Undo]);
+
+        assert_eq!(positions::<Player>(&world), vec![(1, 1)]);
+        assert_eq!(moves_count(&world), 0);
+    }
+
+    #[test]
+    fn pushes_are_counted_apart_from_moves() {
+        let mut world = load(LEVEL);
+        play(&mut world, &[RIGHT, DOWN, Action::Undo]);
+        assert_eq!(pushes_count(&world), 1);
+
+        play(&mut world, &[Action::Undo]);
+        assert_eq!(pushes_count(&world), 0);
+
+        play(&mut world, &[Action::Redo, Action::Redo]);
+        assert_eq!(moves_count(&world), 2);
+        assert_eq!(pushes_count(&world), 1);
+    }
+
+    #[test]
+    fn moves_wait_for_the_slide_to_finish() {
+        let mut world = load(LEVEL);
+        for action in [DOWN, RIGHT].iter() {
+            perform_action(&mut world, *action);
+            run_gameplay(&world);
         }
+        assert_eq!(positions::<Player>(&world), vec![(1, 2)]);
+
+        world.write_resource::<Time>().delta += Duration::from_secs(1);
+        run_gameplay(&world);
+        assert_eq!(positions::<Player>(&world), vec![(2, 2)]);
+    }
+
+    #[test]
+    fn classic_rule_stops_at_two_boxes() {
+        let mut world = load(TWO_BOXES);
+        play(&mut world, &[RIGHT]);
+
+        assert_eq!(positions::<Box>(&world), vec![(2, 1), (3, 1)]);
+        assert_eq!(moves_count(&world), 0);
+    }
+
+    #[test]
+    fn chain_rule_pushes_the_whole_row() {
+        let mut world = load(&format!("PushRule: chain\n{}", TWO_BOXES));
+        play(&mut world, &[RIGHT]);
+
+        assert_eq!(positions::<Box>(&world), vec![(3, 1), (4, 1)]);
+        assert_eq!(moves_count(&world), 1);
+    }
+
+    #[test]
+    fn chains_stop_at_walls() {
+        let mut world = load("PushRule: chain\n#####\n#@$$#\n#..##\n####");
+        play(&mut world, &[RIGHT]);
+
+        assert_eq!(positions::<Player>(&world), vec![(1, 1)]);
+        assert_eq!(positions::<Box>(&world), vec![(2, 1), (3, 1)]);
     }
 }
@@ -0,0 +1,66 @@
+use crate::components::*;
+use crate::resources::Time;
+use specs::{Join, Read, ReadStorage, System, WriteStorage};
+
+/// Turns the player sprite towards its last move, walking while it slides
+pub struct PlayerSpriteSystem {}
+
+impl<'a> System<'a> for PlayerSpriteSystem {
+    type SystemData = (
+        Read<'a, Time>,
+        ReadStorage<'a, Player>,
+        ReadStorage<'a, Tween>,
+        WriteStorage<'a, Renderable>,
+        WriteStorage<'a, Animation>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (time, players, tweens, mut renderables, mut animations) = data;
+
+        for (player, tween, renderable, animation) in (
+            &players,
+            tweens.maybe(),
+            &mut renderables,
+            (&mut animations).maybe(),
+        )
+            .join()
+        {
+            let is_walking = matches!(tween, Some(tween) if !tween.is_finished(time.delta));
+            let state = if is_walking { "walk" } else { "idle" };
+            let is_changed = renderable.set_state(&format!("{}_{}", state, player.facing));
+
+            if let (true, Some(animation)) = (is_changed, animation) {
+                animation.restart(time.delta, AnimationMode::Loop);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::components::{Player, Renderable};
+    use crate::resources::Time;
+    use crate::testing::{load, DOWN};
+    use crate::{perform_action, run_gameplay};
+    use specs::{Join, World, WorldExt};
+    use std::time::Duration;
+
+    fn player_image(world: &World) -> String {
+        let players = world.read_storage::<Player>();
+        let renderables = world.read_storage::<Renderable>();
+        let (_player, renderable) = (&players, &renderables).join().next().unwrap();
+        renderable.path(0)
+    }
+
+    #[test]
+    fn walks_then_stands_facing_the_move() {
+        let mut world = load("#####\n#@$.#\n#   #\n#####");
+        perform_action(&mut world, DOWN);
+        run_gameplay(&world);
+        assert!(player_image(&world).contains("walk_down"));
+
+        world.write_resource::<Time>().delta += Duration::from_secs(1);
+        run_gameplay(&world);
+        assert!(player_image(&world).contains("idle_down"));
+    }
+}
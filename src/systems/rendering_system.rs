@@ -1,12 +1,22 @@
-use crate::{components::*, resources::Gameplay};
-use crate::{constants::*, resources::Time};
+use crate::actions::Direction;
+use crate::components::*;
+use crate::deadlock::DeadSquares;
+use crate::hint::HintSearch;
+use crate::images::ImageCache;
+use crate::keybindings::KeyBindings;
+use crate::resources::{
+    Camera, Deadlocks, DebugOverlay, Gameplay, Hint, HintPush, LevelPack, Replay,
+};
+use crate::{actions::Action, constants::*, resources::Time};
 use ggez::{
-    graphics::{self, spritebatch::SpriteBatch, Color, DrawParam, Image},
+    graphics::{
+        self, spritebatch::SpriteBatch, Color, DrawMode, DrawParam, Mesh, MeshBuilder, Rect,
+    },
     nalgebra, timer, Context,
 };
 use itertools::Itertools;
-use specs::{Join, Read, ReadStorage, System};
-use std::{collections::HashMap, time::Duration};
+use specs::{Entities, Join, Read, ReadStorage, System, Write};
+use std::collections::HashMap;
 
 pub struct RenderingSystem<'a> {
     pub context: &'a mut Context,
@@ -14,28 +24,106 @@ pub struct RenderingSystem<'a> {
 
 impl<'a> System<'a> for RenderingSystem<'a> {
     type SystemData = (
+        Read<'a, Camera>,
+        Read<'a, DeadSquares>,
+        Read<'a, Deadlocks>,
+        Read<'a, DebugOverlay>,
         Read<'a, Gameplay>,
+        Read<'a, Hint>,
+        Read<'a, HintSearch>,
+        Read<'a, KeyBindings>,
+        Read<'a, LevelPack>,
+        Read<'a, Replay>,
         Read<'a, Time>,
+        Write<'a, ImageCache>,
+        Entities<'a>,
         ReadStorage<'a, Position>,
         ReadStorage<'a, Renderable>,
+        ReadStorage<'a, Tween>,
+        ReadStorage<'a, Animation>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (gameplay, time, positions, renderables) = data;
+        let (
+            camera,
+            dead_squares,
+            deadlocks,
+            debug_overlay,
+            gameplay,
+            hint,
+            hint_search,
+            key_bindings,
+            level_pack,
+            replay,
+            time,
+            mut image_cache,
+            entities,
+            positions,
+            renderables,
+            tweens,
+            animations,
+        ) = data;
 
         graphics::clear(self.context, graphics::Color::new(0.95, 0.95, 0.95, 1.0));
 
-        let rendering_data: Vec<_> = (&positions, &renderables).join().collect();
+        if level_pack.is_complete() {
+            let summary = format!(
+                "{} moves, {} pushes",
+                level_pack.total_moves, level_pack.total_pushes
+            );
+            // Around the middle of the window
+            let (x, y) = (camera.viewport.0 / 2.0 - 50.0, camera.viewport.1 / 2.0);
+            self.draw_text("Pack complete!", x, y - 20.0);
+            self.draw_text(&summary, x, y);
+            graphics::present(self.context).expect("Render error");
+            return;
+        }
+
+        if let Some(load_error) = &level_pack.load_error {
+            let title = format!("Level {} could not be loaded", level_pack.current + 1);
+            let y = camera.viewport.1 / 2.0;
+            self.draw_text(&title, 50.0, y - 40.0);
+            self.draw_text(load_error, 50.0, y - 20.0);
+            if let Some(key) = key_bindings.key_name(Action::NextLevel) {
+                self.draw_text(&format!("Press {} to skip it", key), 50.0, y);
+            }
+            graphics::present(self.context).expect("Render error");
+            return;
+        }
+
+        // Everything in the level goes through the camera, the HUD doesn't
+        let (offset_x, offset_y) = camera.offset();
+        let camera_param = DrawParam::new()
+            .dest(nalgebra::Point2::new(offset_x, offset_y))
+            .scale(nalgebra::Vector2::new(camera.zoom, camera.zoom));
+
+        let rendering_data: Vec<_> = (
+            &entities,
+            &positions,
+            &renderables,
+            tweens.maybe(),
+            animations.maybe(),
+        )
+            .join()
+            .collect();
         // {z: u8, map: { path: string, draw_params: [] }}
         let mut rendering_batches: HashMap<u8, HashMap<String, Vec<DrawParam>>> = HashMap::new();
 
-        for (position, renderable) in rendering_data.iter() {
-            let image = self.get_image(&renderable, time.delta);
-            let x = position.x as f32 * TILE_WIDTH;
-            let y = position.y as f32 * TILE_WIDTH;
+        for (entity, position, renderable, tween, animation) in rendering_data.iter() {
+            let image = renderable.path(animation.map_or(0, |animation| animation.frame));
+            // Slide from the previous tile, the position itself is already updated
+            let (x, y) = match tween {
+                Some(tween) => tween.position(position, time.delta),
+                None => (position.x as f32, position.y as f32),
+            };
+            let (x, y) = (x * TILE_WIDTH, y * TILE_WIDTH);
             let z = position.z;
 
-            let draw_params = graphics::DrawParam::new().dest(nalgebra::Point2::new(x, y));
+            let mut draw_params = graphics::DrawParam::new().dest(nalgebra::Point2::new(x, y));
+            // Stuck boxes are tinted red
+            if deadlocks.boxes.contains(&entity.id()) {
+                draw_params = draw_params.color(Color::new(1.0, 0.35, 0.35, 1.0));
+            }
             rendering_batches
                 .entry(z)
                 .or_default()
@@ -49,30 +137,143 @@ impl<'a> System<'a> for RenderingSystem<'a> {
             .sorted_by(|a, b| Ord::cmp(&a.0, &b.0))
         {
             for (image_path, draw_params) in group {
-                let image = Image::new(self.context, image_path).expect("Image error");
+                let image = image_cache.get(self.context, image_path);
                 let mut sprite_batch = SpriteBatch::new(image);
 
                 for draw_param in draw_params.iter() {
                     sprite_batch.add(*draw_param);
                 }
 
-                graphics::draw(self.context, &sprite_batch, graphics::DrawParam::new())
-                    .expect("Draw error");
+                graphics::draw(self.context, &sprite_batch, camera_param).expect("Draw error");
             }
         }
 
-        self.draw_text(&gameplay.state.to_string(), 525.0, 80.0);
-        self.draw_text(&gameplay.moves_count.to_string(), 525.0, 100.0);
+        if debug_overlay.show_dead_squares {
+            self.draw_dead_squares(&dead_squares, camera_param);
+        }
+
+        if let Some(push) = hint.push {
+            self.draw_hint_arrow(push, camera_param);
+        }
+
+        let level = format!(
+            "Level {}/{}",
+            level_pack.current + 1,
+            level_pack.levels.len()
+        );
+        let hud_x = camera.viewport.0 - HUD_WIDTH;
+        self.draw_text(&level, hud_x, 60.0);
+        self.draw_text(&gameplay.state.to_string(), hud_x, 80.0);
+        let moves = format!("Moves: {}", gameplay.moves_count);
+        let pushes = format!("Pushes: {}", gameplay.pushes_count);
+        self.draw_text(&moves, hud_x, 100.0);
+        self.draw_text(&pushes, hud_x, 120.0);
+        if gameplay.hints_count > 0 {
+            let hints = format!("Hints: {}", gameplay.hints_count);
+            self.draw_text(&hints, hud_x, 160.0);
+        }
+        if hint_search.is_running() {
+            self.draw_text("Looking for a hint...", hud_x, 220.0);
+        } else if let Some(error) = &hint.error {
+            self.draw_text(error, hud_x, 220.0);
+        }
 
         // fps meter
         let fps = format!("fps: {:.0}", timer::fps(self.context));
-        self.draw_text(&fps, 525.0, 120.0);
+        self.draw_text(&fps, hud_x, 140.0);
+
+        if replay.is_active() {
+            let status = if replay.is_finished() {
+                "done"
+            } else if replay.paused {
+                "paused"
+            } else if replay.fast_forward {
+                "fast"
+            } else {
+                "playing"
+            };
+            let progress = format!("Replay {}/{} {}", replay.cursor, replay.steps.len(), status);
+            self.draw_text(&progress, hud_x, 180.0);
+            let controls = [
+                (Action::ToggleReplayPause, "pause"),
+                (Action::StepReplay, "step"),
+                (Action::ToggleReplayFastForward, "fast"),
+            ]
+            .iter()
+            .filter_map(|(action, label)| {
+                let key = key_bindings.key_name(*action)?;
+                Some(format!("{}: {}", key, label))
+            })
+            .join("  ");
+            self.draw_text(&controls, hud_x, 200.0);
+        }
 
         graphics::present(self.context).expect("Render error");
     }
 }
 
 impl RenderingSystem<'_> {
+    // Red for cells no box can leave, orange for cells only some colours can't
+    pub fn draw_dead_squares(&mut self, dead_squares: &DeadSquares, camera_param: DrawParam) {
+        let mut counts: HashMap<(u8, u8), usize> = HashMap::new();
+        for cells in dead_squares.cells.values() {
+            for cell in cells {
+                *counts.entry(*cell).or_default() += 1;
+            }
+        }
+        if counts.is_empty() {
+            return;
+        }
+
+        let mut mesh_builder = MeshBuilder::new();
+        for ((x, y), count) in counts {
+            let color = if count == dead_squares.cells.len() {
+                Color::new(1.0, 0.0, 0.0, 0.35)
+            } else {
+                Color::new(1.0, 0.6, 0.0, 0.35)
+            };
+            let rect = Rect::new(
+                x as f32 * TILE_WIDTH,
+                y as f32 * TILE_WIDTH,
+                TILE_WIDTH,
+                TILE_WIDTH,
+            );
+            mesh_builder.rectangle(DrawMode::fill(), rect, color);
+        }
+
+        let overlay = mesh_builder.build(self.context).expect("Mesh error");
+        graphics::draw(self.context, &overlay, camera_param).expect("Draw error");
+    }
+
+    // Triangle over the box pointing where it should be pushed
+    pub fn draw_hint_arrow(&mut self, ((x, y), direction): HintPush, camera_param: DrawParam) {
+        let center_x = (x as f32 + 0.5) * TILE_WIDTH;
+        let center_y = (y as f32 + 0.5) * TILE_WIDTH;
+        let size = TILE_WIDTH / 3.0;
+        let (dx, dy) = match direction {
+            Direction::Up => (0.0, -1.0),
+            Direction::Down => (0.0, 1.0),
+            Direction::Left => (-1.0, 0.0),
+            Direction::Right => (1.0, 0.0),
+        };
+
+        let points = [
+            nalgebra::Point2::new(center_x + dx * size, center_y + dy * size),
+            nalgebra::Point2::new(
+                center_x - dx * size - dy * size,
+                center_y - dy * size + dx * size,
+            ),
+            nalgebra::Point2::new(
+                center_x - dx * size + dy * size,
+                center_y - dy * size - dx * size,
+            ),
+        ];
+        let color = Color::new(0.1, 0.6, 1.0, 0.8);
+        let arrow =
+            Mesh::new_polygon(self.context, DrawMode::fill(), &points, color).expect("Mesh error");
+        graphics::draw(self.context, &arrow, camera_param).expect("Draw error");
+    }
+
     pub fn draw_text(&mut self, text_string: &str, x: f32, y: f32) {
         let text = graphics::Text::new(text_string);
         let destination = nalgebra::Point2::new(x, y);
@@ -88,13 +289,4 @@ impl RenderingSystem<'_> {
         )
         .expect("Draw error");
     }
-
-    pub fn get_image(&mut self, renderable: &Renderable, delta: Duration) -> String {
-        let path_index = match renderable.kind() {
-            RenderableKind::Static => 0,
-            RenderableKind::Animated => ((delta.as_millis() % 1000) / 250) as usize,
-        };
-
-        renderable.path(path_index)
-    }
 }
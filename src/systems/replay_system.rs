@@ -0,0 +1,80 @@
+use crate::actions::Action;
+use crate::resources::{InputQueue, Replay, Time};
+use specs::{Read, System, Write};
+
+pub struct ReplaySystem {}
+
+impl<'a> System<'a> for ReplaySystem {
+    type SystemData = (Write<'a, Replay>, Write<'a, InputQueue>, Read<'a, Time>);
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (mut replay, mut input_queue, time) = data;
+
+        // Steps are timed, so only feed one at a time
+        if replay.is_finished() || !input_queue.actions.is_empty() {
+            return;
+        }
+
+        if replay.paused {
+            if !replay.step_requested {
+                return;
+            }
+            replay.step_requested = false;
+        } else if time.delta < replay.next_step_at {
+            return;
+        }
+
+        if let Some(direction) = replay.steps.get(replay.cursor) {
+            input_queue.actions.push_back(Action::Move(*direction));
+            replay.cursor += 1;
+            replay.next_step_at = time.delta + replay.step_interval();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::actions::Direction;
+    use crate::components::Player;
+    use crate::resources::{Replay, Time};
+    use crate::run_gameplay;
+    use crate::testing::{load, positions};
+    use specs::{World, WorldExt};
+    use std::time::Duration;
+
+    fn start_replay(steps: Vec<Direction>) -> World {
+        let world = load("#######\n#@$  .#\n#######");
+        world.write_resource::<Replay>().steps = steps;
+        world
+    }
+
+    fn wait(world: &World, duration: Duration) {
+        world.write_resource::<Time>().delta += duration;
+        run_gameplay(world);
+    }
+
+    #[test]
+    fn plays_a_step_per_interval() {
+        let world = start_replay(vec![Direction::Right, Direction::Right]);
+        run_gameplay(&world);
+        run_gameplay(&world);
+        assert_eq!(positions::<Player>(&world), vec![(2, 1)]);
+
+        wait(&world, Duration::from_secs(1));
+        assert_eq!(positions::<Player>(&world), vec![(3, 1)]);
+        assert!(world.read_resource::<Replay>().is_finished());
+    }
+
+    #[test]
+    fn paused_replays_play_requested_steps_only() {
+        let world = start_replay(vec![Direction::Right, Direction::Right]);
+        world.write_resource::<Replay>().paused = true;
+        wait(&world, Duration::from_secs(1));
+        assert_eq!(positions::<Player>(&world), vec![(1, 1)]);
+
+        world.write_resource::<Replay>().step_requested = true;
+        wait(&world, Duration::from_secs(1));
+        wait(&world, Duration::from_secs(1));
+        assert_eq!(positions::<Player>(&world), vec![(2, 1)]);
+    }
+}
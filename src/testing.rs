@@ -0,0 +1,46 @@
+//! Levels for the unit tests, played without a window
+
+use crate::actions::{Action, Direction};
+use crate::components::Position;
+use crate::map::{load_map, MapError};
+use crate::resources::Time;
+use crate::{create_world, perform_action, run_gameplay};
+use specs::{Component, Join, World, WorldExt};
+use std::time::Duration;
+
+pub const UP: Action = Action::Move(Direction::Up);
+pub const DOWN: Action = Action::Move(Direction::Down);
+pub const RIGHT: Action = Action::Move(Direction::Right);
+
+pub fn load(map: &str) -> World {
+    let mut world = create_world();
+    load_map(&mut world, map).expect("Invalid test level");
+    world
+}
+
+pub fn load_error(map: &str) -> MapError {
+    load_map(&mut create_world(), map).expect_err("The level should be rejected")
+}
+
+/// Performs the actions one after the other, each gets time to finish sliding
+pub fn play(world: &mut World, actions: &[Action]) {
+    for action in actions {
+        perform_action(world, *action);
+        for _ in 0..2 {
+            run_gameplay(world);
+            world.write_resource::<Time>().delta += Duration::from_secs(1);
+        }
+    }
+}
+
+/// Where the entities with a `T` are, sorted
+pub fn positions<T: Component>(world: &World) -> Vec<(u8, u8)> {
+    let positions = world.read_storage::<Position>();
+    let markers = world.read_storage::<T>();
+    let mut cells: Vec<(u8, u8)> = (&positions, &markers)
+        .join()
+        .map(|(position, _)| (position.x, position.y))
+        .collect();
+    cells.sort_unstable();
+    cells
+}
@@ -0,0 +1,246 @@
+use crate::components::{Box, BoxColor, BoxSpot, Floor, Player, Renderable, Wall};
+use crate::constants::{DEFAULT_THEME, THEMES_PATH};
+use serde::Deserialize;
+use specs::{Join, ReadStorage, World, WorldExt, WriteStorage};
+use std::collections::HashMap;
+use std::fmt;
+use std::fmt::Display;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// Built into the game, so a broken resources directory still has sprites to fall back on
+const DEFAULT_THEME_TOML: &str = include_str!("../resources/themes/default.toml");
+
+/// Frames of every animation state, see Renderable
+pub type Animations = HashMap<String, Vec<String>>;
+
+/// The kinds of entities a theme has sprites for
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ThemeItem {
+    Wall,
+    Floor,
+    Player,
+    Box(BoxColor),
+    BoxSpot(BoxColor),
+}
+
+/// Image paths for every entity kind, read from a theme manifest
+#[derive(Debug, Deserialize)]
+pub struct Theme {
+    // the file name without its extension, used to cycle through the themes
+    #[serde(skip)]
+    pub name: String,
+    #[serde(default)]
+    wall: Animations,
+    #[serde(default)]
+    floor: Animations,
+    #[serde(default)]
+    player: Animations,
+    // keyed by the colour's name
+    #[serde(default, rename = "box")]
+    boxes: HashMap<String, Animations>,
+    #[serde(default, rename = "box_spot")]
+    box_spots: HashMap<String, Animations>,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        let mut theme: Theme = toml::from_str(DEFAULT_THEME_TOML).expect("Invalid default theme");
+        theme.name = DEFAULT_THEME.to_string();
+        theme
+    }
+}
+
+impl Theme {
+    /// A Renderable showing `state`, which the default theme has to define
+    pub fn renderable(&self, item: ThemeItem, state: &str) -> Renderable {
+        let states = self
+            .animations(item)
+            .iter()
+            .map(|(state, paths)| (state.clone(), paths.clone()))
+            .collect();
+        Renderable::new_states(state, states)
+    }
+
+    fn animations(&self, item: ThemeItem) -> &Animations {
+        match item {
+            ThemeItem::Wall => &self.wall,
+            ThemeItem::Floor => &self.floor,
+            ThemeItem::Player => &self.player,
+            ThemeItem::Box(color) => &self.boxes[&color.to_string()],
+            ThemeItem::BoxSpot(color) => &self.box_spots[&color.to_string()],
+        }
+    }
+
+    // Whatever this theme leaves out comes from the other one, state by state
+    fn merge(&mut self, fallback: Theme) {
+        merge_animations(&mut self.wall, fallback.wall);
+        merge_animations(&mut self.floor, fallback.floor);
+        merge_animations(&mut self.player, fallback.player);
+        for (color, animations) in fallback.boxes {
+            merge_animations(self.boxes.entry(color).or_default(), animations);
+        }
+        for (color, animations) in fallback.box_spots {
+            merge_animations(self.box_spots.entry(color).or_default(), animations);
+        }
+    }
+}
+
+// A state without frames counts as missing, there would be nothing to draw
+fn merge_animations(animations: &mut Animations, fallback: Animations) {
+    for (state, paths) in fallback {
+        let frames = animations.entry(state).or_default();
+        if frames.is_empty() {
+            *frames = paths;
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ThemeError {
+    Io {
+        path: PathBuf,
+        error: io::Error,
+    },
+    Parse {
+        path: PathBuf,
+        error: toml::de::Error,
+    },
+}
+
+impl Display for ThemeError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ThemeError::Io { path, error } => {
+                write!(fmt, "Could not read theme {}: {}", path.display(), error)
+            }
+            ThemeError::Parse { path, error } => {
+                write!(fmt, "Invalid theme {}: {}", path.display(), error)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ThemeError {}
+
+/// Reads a theme manifest, anything it doesn't define is taken from the default theme
+pub fn load_theme(path: &Path) -> Result<Theme, ThemeError> {
+    let content = fs::read_to_string(path).map_err(|error| ThemeError::Io {
+        path: path.to_path_buf(),
+        error,
+    })?;
+    let mut theme: Theme = toml::from_str(&content).map_err(|error| ThemeError::Parse {
+        path: path.to_path_buf(),
+        error,
+    })?;
+
+    theme.name = theme_name(path);
+    theme.merge(Theme::default());
+    Ok(theme)
+}
+
+fn theme_name(path: &Path) -> String {
+    path.file_stem()
+        .map_or_else(String::new, |x| x.to_string_lossy().into_owned())
+}
+
+// The manifests in the themes directory, in the order they are cycled through
+fn theme_paths(themes_path: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    for entry in fs::read_dir(themes_path)? {
+        let path = entry?.path();
+        if matches!(path.extension(), Some(x) if x == "toml") {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+
+    Ok(paths)
+}
+
+/// Switches to the theme after the current one in the themes directory
+pub fn next_theme(world: &mut World) {
+    let paths = match theme_paths(Path::new(THEMES_PATH)) {
+        Ok(paths) => paths,
+        Err(error) => {
+            eprintln!("Could not list themes in {}: {}", THEMES_PATH, error);
+            return;
+        }
+    };
+    if paths.is_empty() {
+        return;
+    }
+
+    // A level pack's own theme isn't in the list, it goes on with the first one
+    let current = world.read_resource::<Theme>().name.clone();
+    let next = paths
+        .iter()
+        .position(|path| theme_name(path) == current)
+        .map_or(0, |index| (index + 1) % paths.len());
+
+    match load_theme(&paths[next]) {
+        Ok(theme) => {
+            world.insert(theme);
+            apply_theme(world);
+        }
+        Err(error) => eprintln!("{}", error),
+    }
+}
+
+/// Gives every entity of the level its sprites from the current theme,
+/// the entities keep their animation state
+pub fn apply_theme(world: &mut World) {
+    let theme = world.read_resource::<Theme>();
+    let walls: ReadStorage<Wall> = world.read_storage();
+    let floors: ReadStorage<Floor> = world.read_storage();
+    let players: ReadStorage<Player> = world.read_storage();
+    let boxes: ReadStorage<Box> = world.read_storage();
+    let box_spots: ReadStorage<BoxSpot> = world.read_storage();
+    let mut renderables: WriteStorage<Renderable> = world.write_storage();
+
+    for (renderable, wall, floor, player, box_, box_spot) in (
+        &mut renderables,
+        walls.maybe(),
+        floors.maybe(),
+        players.maybe(),
+        boxes.maybe(),
+        box_spots.maybe(),
+    )
+        .join()
+    {
+        let item = match (wall, floor, player, box_, box_spot) {
+            (Some(_), ..) => ThemeItem::Wall,
+            (_, Some(_), ..) => ThemeItem::Floor,
+            (_, _, Some(_), ..) => ThemeItem::Player,
+            (_, _, _, Some(box_), _) => ThemeItem::Box(box_.color),
+            (.., Some(box_spot)) => ThemeItem::BoxSpot(box_spot.color),
+            _ => continue,
+        };
+
+        let state = renderable.state().to_string();
+        *renderable = theme.renderable(item, &state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_sprites_come_from_the_default_theme() {
+        let mut theme: Theme =
+            toml::from_str("[player]\nidle_down = [\"/images/still.png\"]\nwalk_down = []")
+                .unwrap();
+        theme.merge(Theme::default());
+        let default_theme = Theme::default();
+
+        assert_eq!(theme.player["idle_down"], vec!["/images/still.png"]);
+        assert_eq!(theme.player["walk_down"], default_theme.player["walk_down"]);
+        assert_eq!(theme.wall, default_theme.wall);
+        assert_eq!(
+            theme.boxes[&BoxColor::Blue.to_string()],
+            default_theme.boxes[&BoxColor::Blue.to_string()]
+        );
+    }
+}
@@ -0,0 +1,35 @@
+//! Plays a level pack through the library, without a window, GPU or audio device.
+
+use rust_sokoban::actions::{Action, Direction};
+use rust_sokoban::level::{finish_level, initialize_level, is_level_won};
+use rust_sokoban::resources::{LevelPack, SoundQueue, Time};
+use rust_sokoban::{create_world, perform_action, run_gameplay};
+use specs::WorldExt;
+use std::time::Duration;
+
+#[test]
+fn plays_a_pack_without_a_window() {
+    let mut world = create_world();
+    let levels = vec![
+        String::from("######\n#@$ .#\n######"),
+        String::from("#####\n#@$.#\n#####"),
+    ];
+    initialize_level(&mut world, String::from("headless"), levels, 0);
+
+    // Every move gets time to finish sliding before the next one
+    for _ in 0..2 {
+        perform_action(&mut world, Action::Move(Direction::Right));
+        run_gameplay(&world);
+        world.write_resource::<Time>().delta += Duration::from_secs(1);
+    }
+    run_gameplay(&world);
+    assert!(is_level_won(&world));
+
+    // Sounds wait in a queue for a binary that can play them
+    assert_eq!(world.read_resource::<SoundQueue>().sounds, vec!["correct"]);
+
+    let score = finish_level(&mut world);
+    assert_eq!((score.moves_count, score.pushes_count), (2, 2));
+    assert_eq!(world.read_resource::<LevelPack>().current, 1);
+    assert!(!is_level_won(&world));
+}